@@ -5,7 +5,7 @@
  * Thunderbird API Extension
  * Exposes email, calendar, and contacts via JSON-RPC over HTTP.
  *
- * Architecture: thunderbird-cli / thunderbird-api bridge --> This extension (port 8766)
+ * Architecture: thunderbird-cli / thunderbird-api bridge --> This extension (port 8756)
  *
  * Key quirks documented inline:
  * - MIME header decoding (mime2Decoded* properties)
@@ -18,7 +18,9 @@ const resProto = Cc[
   "@mozilla.org/network/protocol;1?name=resource"
 ].getService(Ci.nsISubstitutingProtocolHandler);
 
-const API_PORT = 8756;
+const DEFAULT_API_PORT = 8756;
+// Set in about:config to run several profiles side by side.
+const API_PORT_PREF = "extensions.thunderbird-api.port";
 const DEFAULT_MAX_RESULTS = 50;
 const MAX_SEARCH_RESULTS_CAP = 200;
 const SEARCH_COLLECTION_CAP = 1000;
@@ -433,7 +435,7 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               return { msgHdr, folder };
             }
 
-            function searchMessages(query, startDate, endDate, maxResults, sortOrder) {
+            async function searchMessages(query, startDate, endDate, maxResults, sortOrder, report) {
               const results = [];
               const lowerQuery = (query || "").toLowerCase();
               const hasQuery = !!lowerQuery;
@@ -451,8 +453,6 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               const normalizedSortOrder = sortOrder === "asc" ? "asc" : "desc";
 
               function searchFolder(folder) {
-                if (results.length >= SEARCH_COLLECTION_CAP) return;
-
                 try {
                   // Attempt to refresh IMAP folders. This is async and may not
                   // complete before we read, but helps with stale data.
@@ -505,18 +505,28 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                 } catch {
                   // Skip inaccessible folders
                 }
+              }
 
+              // List folders up front so progress has a total
+              const folders = [];
+              function collectFolders(folder) {
+                folders.push(folder);
                 if (folder.hasSubFolders) {
                   for (const subfolder of folder.subFolders) {
-                    if (results.length >= SEARCH_COLLECTION_CAP) break;
-                    searchFolder(subfolder);
+                    collectFolders(subfolder);
                   }
                 }
               }
-
               for (const account of MailServices.accounts.accounts) {
+                collectFolders(account.incomingServer.rootFolder);
+              }
+
+              for (const [i, folder] of folders.entries()) {
                 if (results.length >= SEARCH_COLLECTION_CAP) break;
-                searchFolder(account.incomingServer.rootFolder);
+                report(i, folders.length, `Searched ${i} of ${folders.length} folders`);
+                // Let the server answer getProgress between folders
+                await yieldToEventLoop();
+                searchFolder(folder);
               }
 
               results.sort((a, b) => normalizedSortOrder === "asc" ? a._dateTs - b._dateTs : b._dateTs - a._dateTs);
@@ -720,7 +730,7 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
 
             const MAX_ATTACHMENT_SIZE = 50 * 1024 * 1024; // 50MB
 
-            function getMessage(messageId, folderPath, saveAttachments) {
+            function getMessage(messageId, folderPath, saveAttachments, report) {
               return new Promise((resolve) => {
                 try {
                   const found = findMessage(messageId, folderPath);
@@ -800,6 +810,8 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                       if (!tmpDir.exists()) tmpDir.create(Ci.nsIFile.DIRECTORY_TYPE, 0o755);
 
                       for (const att of rawAttachments) {
+                        report(attachments.length, rawAttachments.length,
+                          `Saved ${attachments.length} of ${rawAttachments.length} attachments`);
                         const attInfo = {
                           name: att.name,
                           contentType: att.contentType,
@@ -1271,14 +1283,37 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               });
             }
 
+            /**
+             * Progress of long-running calls, by the progressToken the bridge
+             * puts in params._meta, for getProgress. Only searchMessages and
+             * getMessage with saveAttachments report any.
+             */
+            const progress = new Map();
+
+            function yieldToEventLoop() {
+              return new Promise(resolve => Services.tm.dispatchToMainThread(resolve));
+            }
+
             async function callTool(name, args) {
+              const token = args._meta ? args._meta.progressToken : undefined;
+              const report = token === undefined
+                ? () => {}
+                : (done, total, message) => progress.set(String(token), { progress: done, total, message });
+              try {
+                return await runTool(name, args, report);
+              } finally {
+                if (token !== undefined) progress.delete(String(token));
+              }
+            }
+
+            async function runTool(name, args, report) {
               switch (name) {
                 case "listAccounts":
                   return listAccounts();
                 case "searchMessages":
-                  return searchMessages(args.query || "", args.startDate, args.endDate, args.maxResults, args.sortOrder);
+                  return await searchMessages(args.query || "", args.startDate, args.endDate, args.maxResults, args.sortOrder, report);
                 case "getMessage":
-                  return await getMessage(args.messageId, args.folderPath, args.saveAttachments);
+                  return await getMessage(args.messageId, args.folderPath, args.saveAttachments, report);
                 case "searchContacts":
                   return searchContacts(args.query || "");
                 case "listCalendars":
@@ -1300,6 +1335,113 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               }
             }
 
+            /**
+             * Records the listening port so clients can find it without configuration.
+             * Path must match port_file_path() in src/config.rs:
+             * $XDG_RUNTIME_DIR/thunderbird-api/port, else <TmpD>/thunderbird-api/port.
+             * Every profile writes the same file, so the one started last wins.
+             */
+            function writePortFile(port) {
+              try {
+                const env = Cc["@mozilla.org/process/environment;1"].getService(Ci.nsIEnvironment);
+                let dir;
+                const runtimeDir = env.exists("XDG_RUNTIME_DIR") ? env.get("XDG_RUNTIME_DIR") : "";
+                if (runtimeDir) {
+                  dir = Cc["@mozilla.org/file/local;1"].createInstance(Ci.nsIFile);
+                  dir.initWithPath(runtimeDir);
+                } else {
+                  dir = Cc["@mozilla.org/file/directory_service;1"]
+                    .getService(Ci.nsIProperties)
+                    .get("TmpD", Ci.nsIFile);
+                }
+                dir.append("thunderbird-api");
+                if (!dir.exists()) dir.create(Ci.nsIFile.DIRECTORY_TYPE, 0o700);
+
+                const portFile = dir.clone();
+                portFile.append("port");
+                const data = `${port}\n`;
+                const fos = Cc["@mozilla.org/network/file-output-stream;1"]
+                  .createInstance(Ci.nsIFileOutputStream);
+                fos.init(portFile, 0x02 | 0x08 | 0x20, 0o644, 0);
+                fos.write(data, data.length);
+                fos.close();
+              } catch (e) {
+                console.error("Failed to write port file:", e);
+              }
+            }
+
+            /**
+             * Directory for state shared with the Rust clients.
+             * Must match config_dir() in src/config.rs:
+             * $XDG_CONFIG_HOME/thunderbird-api, else ~/.config/thunderbird-api.
+             */
+            function configDir() {
+              const env = Cc["@mozilla.org/process/environment;1"].getService(Ci.nsIEnvironment);
+              const configHome = env.exists("XDG_CONFIG_HOME") ? env.get("XDG_CONFIG_HOME") : "";
+              let dir;
+              if (configHome) {
+                dir = Cc["@mozilla.org/file/local;1"].createInstance(Ci.nsIFile);
+                dir.initWithPath(configHome);
+              } else {
+                dir = Cc["@mozilla.org/file/directory_service;1"]
+                  .getService(Ci.nsIProperties)
+                  .get("Home", Ci.nsIFile);
+                dir.append(".config");
+              }
+              dir.append("thunderbird-api");
+              return dir;
+            }
+
+            /**
+             * Loads the bearer token clients must send, generating it on first run.
+             * Path must match token_file_path() in src/config.rs. The file is
+             * mode 0600 so only this user's processes can drive the API.
+             */
+            function loadOrCreateToken() {
+              const dir = configDir();
+              if (!dir.exists()) dir.create(Ci.nsIFile.DIRECTORY_TYPE, 0o700);
+              const tokenFile = dir.clone();
+              tokenFile.append("token");
+
+              if (tokenFile.exists()) {
+                const fis = Cc["@mozilla.org/network/file-input-stream;1"]
+                  .createInstance(Ci.nsIFileInputStream);
+                fis.init(tokenFile, 0x01, 0, 0);
+                const existing = NetUtil.readInputStreamToString(fis, fis.available(), { charset: "UTF-8" }).trim();
+                fis.close();
+                if (existing) {
+                  tokenFile.permissions = 0o600;
+                  return existing;
+                }
+              }
+
+              const bytes = Cc["@mozilla.org/security/random-generator;1"]
+                .getService(Ci.nsIRandomGenerator)
+                .generateRandomBytes(32);
+              const token = Array.from(bytes, b => b.toString(16).padStart(2, "0")).join("");
+              const data = `${token}\n`;
+              const fos = Cc["@mozilla.org/network/file-output-stream;1"]
+                .createInstance(Ci.nsIFileOutputStream);
+              fos.init(tokenFile, 0x02 | 0x08 | 0x20, 0o600, 0);
+              fos.write(data, data.length);
+              fos.close();
+              tokenFile.permissions = 0o600;
+              return token;
+            }
+
+            // Constant-time comparison, so the token can't be guessed byte by byte.
+            function tokenMatches(given, expected) {
+              if (given.length !== expected.length) return false;
+              let diff = 0;
+              for (let i = 0; i < given.length; i++) {
+                diff |= given.charCodeAt(i) ^ expected.charCodeAt(i);
+              }
+              return diff === 0;
+            }
+
+            // Fail closed: without a token file, don't serve at all.
+            const apiToken = loadOrCreateToken();
+
             const server = new HttpServer();
 
             server.registerPathHandler("/", (req, res) => {
@@ -1312,6 +1454,15 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                 return;
               }
 
+              const auth = req.hasHeader("Authorization") ? req.getHeader("Authorization") : "";
+              if (!auth.startsWith("Bearer ") || !tokenMatches(auth.slice(7).trim(), apiToken)) {
+                res.setStatusLine("1.1", 401, "Unauthorized");
+                res.setHeader("WWW-Authenticate", "Bearer", false);
+                res.write(auth ? "Invalid API token" : "Missing API token");
+                res.finish();
+                return;
+              }
+
               let message;
               try {
                 message = JSON.parse(readRequestBody(req));
@@ -1329,6 +1480,8 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
                   let result;
                   if (method === "listTools") {
                     result = { tools };
+                  } else if (method === "getProgress") {
+                    result = progress.get(String((params || {}).progressToken)) || null;
                   } else {
                     result = await callTool(method, params || {});
                   }
@@ -1349,9 +1502,11 @@ var mcpServer = class extends ExtensionCommon.ExtensionAPI {
               })();
             });
 
-            server.start(API_PORT);
-            console.log(`Thunderbird API server listening on port ${API_PORT}`);
-            return { success: true, port: API_PORT };
+            const apiPort = Services.prefs.getIntPref(API_PORT_PREF, DEFAULT_API_PORT);
+            server.start(apiPort);
+            writePortFile(apiPort);
+            console.log(`Thunderbird API server listening on port ${apiPort}`);
+            return { success: true, port: apiPort };
           } catch (e) {
             console.error("Failed to start API server:", e);
             return { success: false, error: e.toString() };
@@ -0,0 +1,221 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::client::{
+    decode_result, encode_args, encode_request, parse_response, tool_request, tool_result,
+};
+use crate::config::ClientConfig;
+use crate::error::{Result, ThunderbirdError};
+use crate::model::{
+    Account, Calendar, ComposeResult, Contact, CreateEventArgs, Folder, ForwardArgs,
+    GetMessageArgs, ListFoldersArgs, Message, MessageSummary, ReplyArgs, SearchArgs,
+    SearchContactsArgs, SendMailArgs, UpdateMessageArgs, UpdateResult,
+};
+use crate::retry::RetryPolicy;
+use crate::transport::{check_status, read_error, too_large};
+use crate::types::{JsonRpcRequest, JsonRpcResponse};
+
+/// Non-blocking counterpart of [`ThunderbirdClient`](crate::client::ThunderbirdClient).
+/// Must be used from within a tokio runtime.
+///
+/// Build it with [`ClientBuilder::build_async`](crate::client::ClientBuilder::build_async)
+/// to get the same endpoint discovery as the blocking client.
+#[derive(Clone)]
+pub struct AsyncThunderbirdClient {
+    http: reqwest::Client,
+    url: String,
+    retry: RetryPolicy,
+    token: Option<String>,
+    max_response_size: u64,
+}
+
+impl Default for AsyncThunderbirdClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncThunderbirdClient {
+    /// Client for the default endpoint, without discovery.
+    pub fn new() -> Self {
+        Self::with_config(&ClientConfig::default())
+    }
+
+    pub fn with_config(config: &ClientConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .expect("reqwest client without TLS cannot fail to build");
+        Self {
+            http,
+            url: config.endpoint.clone(),
+            retry: config.retry.clone(),
+            token: config.token.clone(),
+            max_response_size: config.max_response_size,
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    /// Send a raw JSON-RPC request and return the parsed response.
+    /// Uses sanitize_json as fallback if the response contains invalid control chars,
+    /// and replaces invalid UTF-8 rather than failing.
+    ///
+    /// A JSON-RPC error object from the extension is returned as `Ok`, so it
+    /// can be passed through unchanged; transport and parse failures are `Err`.
+    ///
+    /// Read-only methods are retried according to the client's [`RetryPolicy`].
+    pub async fn send_raw(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let body = encode_request(request)?;
+        let mut backoff = self.retry.backoff();
+        loop {
+            match self.post(&body).await {
+                Err(e) if self.retry.should_retry(&request.method, &e) => {
+                    match backoff.next_delay() {
+                        Some(delay) => tokio::time::sleep(delay).await,
+                        None => return Err(e),
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn post(&self, body: &str) -> Result<JsonRpcResponse> {
+        let mut post = self
+            .http
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(token) = &self.token {
+            post = post.bearer_auth(token);
+        }
+        let mut response = post
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| self.transport_error(e))?;
+
+        let status = response.status();
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_response_size)
+        {
+            return Err(too_large(self.max_response_size));
+        }
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| {
+            if e.is_timeout() {
+                self.transport_error(e)
+            } else {
+                read_error(e)
+            }
+        })? {
+            if (data.len() + chunk.len()) as u64 > self.max_response_size {
+                return Err(too_large(self.max_response_size));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        check_status(status.as_u16(), &data)?;
+        parse_response(&data).map(|parsed| parsed.response)
+    }
+
+    fn transport_error(&self, e: reqwest::Error) -> ThunderbirdError {
+        if e.is_timeout() {
+            ThunderbirdError::Timeout {
+                endpoint: self.url.clone(),
+            }
+        } else {
+            ThunderbirdError::NotRunning {
+                endpoint: self.url.clone(),
+                message: e.to_string(),
+            }
+        }
+    }
+
+    /// Call a tool on the Thunderbird extension and return the result directly.
+    /// JSON-RPC errors and `{ "error": ... }` results are both returned as errors.
+    pub async fn call_tool(&self, name: &str, args: Value) -> Result<Value> {
+        tool_result(self.send_raw(&tool_request(name, args)).await?)
+    }
+
+    /// Call a tool with typed arguments and deserialize its result.
+    pub async fn call<A: Serialize, R: DeserializeOwned>(&self, name: &str, args: &A) -> Result<R> {
+        let result = self.call_tool(name, encode_args(name, args)?).await?;
+        decode_result(name, result)
+    }
+
+    pub async fn list_accounts(&self) -> Result<Vec<Account>> {
+        self.call("listAccounts", &serde_json::json!({})).await
+    }
+
+    pub async fn search_messages(&self, args: &SearchArgs) -> Result<Vec<MessageSummary>> {
+        self.call("searchMessages", args).await
+    }
+
+    pub async fn get_message(&self, args: &GetMessageArgs) -> Result<Message> {
+        self.call("getMessage", args).await
+    }
+
+    pub async fn list_folders(&self, args: &ListFoldersArgs) -> Result<Vec<Folder>> {
+        self.call("listFolders", args).await
+    }
+
+    pub async fn update_message(&self, args: &UpdateMessageArgs) -> Result<UpdateResult> {
+        self.call("updateMessage", args).await
+    }
+
+    pub async fn send_mail(&self, args: &SendMailArgs) -> Result<ComposeResult> {
+        self.call("sendMail", args).await
+    }
+
+    pub async fn reply_to_message(&self, args: &ReplyArgs) -> Result<ComposeResult> {
+        self.call("replyToMessage", args).await
+    }
+
+    pub async fn forward_message(&self, args: &ForwardArgs) -> Result<ComposeResult> {
+        self.call("forwardMessage", args).await
+    }
+
+    pub async fn search_contacts(&self, args: &SearchContactsArgs) -> Result<Vec<Contact>> {
+        self.call("searchContacts", args).await
+    }
+
+    pub async fn list_calendars(&self) -> Result<Vec<Calendar>> {
+        self.call("listCalendars", &serde_json::json!({})).await
+    }
+
+    pub async fn create_event(&self, args: &CreateEventArgs) -> Result<ComposeResult> {
+        self.call("createEvent", args).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn connection_refused_is_not_running() {
+        // Bind and drop to get a port nothing is listening on.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = AsyncThunderbirdClient::with_config(&ClientConfig {
+            endpoint: format!("http://127.0.0.1:{}/", port),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        });
+        let err = client.list_accounts().await.unwrap_err();
+        assert!(
+            matches!(err, ThunderbirdError::NotRunning { .. }),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Which local files the compose tools may attach. `sendMail`,
+//! `replyToMessage` and `forwardMessage` take paths that Thunderbird reads
+//! and attaches, so an agent talked into it could put `~/.ssh/id_ed25519`
+//! into a draft.
+//!
+//! Before such a call is sent, every attachment is resolved, symlinks
+//! included, and refused if it:
+//! - is not an absolute path, which the bridge would resolve against its own
+//!   working directory rather than the user's
+//! - matches a denied pattern, either as given or resolved
+//! - lies outside the allowed directories, when any are configured
+//! - is not a regular file, or is larger than the size limit
+//!
+//! A pattern without `/` is matched against each component of the path, so
+//! `.*` covers `~/.env` as well as everything under `~/.ssh`. A pattern with
+//! `/` is matched against the whole path. `*` matches any run of characters
+//! and `?` any one, ignoring ASCII case.
+//!
+//! A refused call is answered the way a failing tool would be, with
+//! `{ "error": ... }`, and never reaches Thunderbird. That includes a missing
+//! file, which the extension would otherwise list under `failed` while
+//! sending the rest. An allowed call is sent
+//! with the resolved paths, so the file checked is the file attached.
+//! `AsyncThunderbirdClient` does not check attachments.
+
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Typical limit of mail servers for a whole message.
+pub const DEFAULT_MAX_SIZE: u64 = 25 * 1024 * 1024;
+
+/// Tools with an `attachments` array of local paths.
+pub const ATTACHING_TOOLS: &[&str] = &["sendMail", "replyToMessage", "forwardMessage"];
+
+/// Dotfiles and dot directories, SSH keys, certificates and key stores.
+pub const DEFAULT_DENY: &[&str] = &[
+    ".*",
+    "id_rsa*",
+    "id_dsa*",
+    "id_ecdsa*",
+    "id_ed25519*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.kdbx",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentPolicy {
+    /// Attachments must lie inside one of these. Empty allows any directory.
+    pub dirs: Vec<PathBuf>,
+    /// Patterns of files never attached.
+    pub deny: Vec<String>,
+    /// Largest file attached, in bytes.
+    pub max_size: u64,
+}
+
+impl Default for AttachmentPolicy {
+    fn default() -> Self {
+        Self {
+            dirs: Vec::new(),
+            deny: DEFAULT_DENY.iter().map(|p| p.to_string()).collect(),
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+impl AttachmentPolicy {
+    /// `params` with every attachment resolved, `None` if `tool` attaches
+    /// nothing, or why an attachment is refused.
+    pub fn check(&self, tool: &str, params: Option<&Value>) -> Result<Option<Value>, String> {
+        if !ATTACHING_TOOLS.contains(&tool) {
+            return Ok(None);
+        }
+        let Some(params) = params else {
+            return Ok(None);
+        };
+        let Some(paths) = params
+            .get("attachments")
+            .and_then(Value::as_array)
+            .filter(|a| !a.is_empty())
+        else {
+            return Ok(None);
+        };
+        let resolved = paths
+            .iter()
+            .map(|path| {
+                let path = path
+                    .as_str()
+                    .ok_or_else(|| format!("Invalid attachment: {}", path))?;
+                let real = self.resolve(Path::new(path))?;
+                Ok(Value::String(real.to_string_lossy().into_owned()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut params = params.clone();
+        params["attachments"] = Value::Array(resolved);
+        Ok(Some(params))
+    }
+
+    /// The real path of one attachment, if it may be attached.
+    pub fn resolve(&self, path: &Path) -> Result<PathBuf, String> {
+        let shown = path.display();
+        if !path.is_absolute() {
+            return Err(format!(
+                "Attachment not allowed: {} is not an absolute path",
+                shown
+            ));
+        }
+        if let Some(pattern) = self.denied(path) {
+            return Err(format!(
+                "Attachment not allowed: {} matches the denied pattern {}",
+                shown, pattern
+            ));
+        }
+        let real = fs::canonicalize(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("Attachment not found: {}", shown),
+            _ => format!("Cannot read attachment {}: {}", shown, e),
+        })?;
+        if let Some(pattern) = self.denied(&real) {
+            return Err(format!(
+                "Attachment not allowed: {} is {}, which matches the denied pattern {}",
+                shown,
+                real.display(),
+                pattern
+            ));
+        }
+        if !self.dirs.is_empty()
+            && !self
+                .dirs
+                .iter()
+                .filter_map(|dir| fs::canonicalize(expand_home(dir)).ok())
+                .any(|dir| real.starts_with(dir))
+        {
+            let dirs: Vec<_> = self.dirs.iter().map(|d| d.display().to_string()).collect();
+            return Err(format!(
+                "Attachment not allowed: {} is outside the allowed directories ({})",
+                shown,
+                dirs.join(", ")
+            ));
+        }
+        let metadata =
+            fs::metadata(&real).map_err(|e| format!("Cannot read attachment {}: {}", shown, e))?;
+        if !metadata.is_file() {
+            return Err(format!(
+                "Attachment not allowed: {} is not a regular file",
+                shown
+            ));
+        }
+        if metadata.len() > self.max_size {
+            return Err(format!(
+                "Attachment not allowed: {} is {}, larger than the {} limit",
+                shown,
+                megabytes(metadata.len()),
+                megabytes(self.max_size)
+            ));
+        }
+        Ok(real)
+    }
+
+    /// The first denied pattern `path` matches.
+    fn denied(&self, path: &Path) -> Option<&str> {
+        let whole = path.to_string_lossy();
+        self.deny.iter().map(String::as_str).find(|pattern| {
+            if pattern.contains('/') {
+                let pattern = expand_home(Path::new(pattern));
+                glob_match(&pattern.to_string_lossy(), &whole)
+            } else {
+                path.components().any(|c| match c {
+                    Component::Normal(name) => glob_match(pattern, &name.to_string_lossy()),
+                    _ => false,
+                })
+            }
+        })
+    }
+}
+
+/// `~/x` as `$HOME/x`.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// `*` matches any run of characters and `?` any one, ignoring ASCII case.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let text: Vec<char> = text.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*` when a match fails
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some((after, from)) = star {
+            p = after;
+            t = from + 1;
+            star = Some((after, from + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A fresh directory with `files` written into it.
+    fn dir(name: &str, files: &[(&str, usize)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "thunderbird-api-attachments-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, size) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, vec![b'x'; *size]).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match(".*", ".ssh"));
+        assert!(!glob_match(".*", "notes.txt"));
+        assert!(glob_match("*.pem", "Server.PEM"));
+        assert!(glob_match("id_ed25519*", "id_ed25519.pub"));
+        assert!(glob_match("/home/*/secret?", "/home/me/docs/secret1"));
+        assert!(!glob_match("a*b", "a-b-c"));
+    }
+
+    #[test]
+    fn denied_patterns_and_size() {
+        let dir = dir(
+            "deny",
+            &[("report.pdf", 10), ("big.iso", 2 << 20), ("server.key", 1)],
+        );
+        let policy = AttachmentPolicy {
+            max_size: 1 << 20,
+            ..AttachmentPolicy::default()
+        };
+        let real = policy.resolve(&dir.join("report.pdf")).unwrap();
+        assert!(real.is_absolute());
+
+        let err = policy.resolve(&dir.join("server.key")).unwrap_err();
+        assert!(err.ends_with("matches the denied pattern *.key"), "{}", err);
+        let err = policy
+            .resolve(Path::new("/home/me/.ssh/config"))
+            .unwrap_err();
+        assert!(err.ends_with("matches the denied pattern .*"), "{}", err);
+        let err = policy.resolve(&dir.join("big.iso")).unwrap_err();
+        assert!(
+            err.ends_with("is 2.0 MB, larger than the 1.0 MB limit"),
+            "{}",
+            err
+        );
+        let err = policy.resolve(&dir).unwrap_err();
+        assert!(err.ends_with("is not a regular file"), "{}", err);
+        let err = policy.resolve(&dir.join("missing.pdf")).unwrap_err();
+        assert!(err.starts_with("Attachment not found: "), "{}", err);
+        let err = policy.resolve(Path::new("report.pdf")).unwrap_err();
+        assert_eq!(
+            err,
+            "Attachment not allowed: report.pdf is not an absolute path"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_resolved() {
+        use std::os::unix::fs::symlink;
+
+        let dir = dir(
+            "symlink",
+            &[
+                ("docs/report.pdf", 1),
+                ("keys/.hidden", 1),
+                ("private/notes.pdf", 1),
+            ],
+        );
+        symlink(dir.join("keys/.hidden"), dir.join("docs/innocent.pdf")).unwrap();
+        symlink(dir.join("private"), dir.join("docs/private")).unwrap();
+
+        let policy = AttachmentPolicy {
+            dirs: vec![dir.join("docs")],
+            deny: vec![],
+            ..AttachmentPolicy::default()
+        };
+        assert!(policy.resolve(&dir.join("docs/report.pdf")).is_ok());
+        let err = policy
+            .resolve(&dir.join("docs/private/notes.pdf"))
+            .unwrap_err();
+        assert!(
+            err.contains("is outside the allowed directories"),
+            "{}",
+            err
+        );
+
+        let err = AttachmentPolicy::default()
+            .resolve(&dir.join("docs/innocent.pdf"))
+            .unwrap_err();
+        assert!(
+            err.ends_with("which matches the denied pattern .*"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn only_attaching_tools_are_checked() {
+        let dir = dir("check", &[("a.txt", 1)]);
+        let policy = AttachmentPolicy::default();
+        let file = dir.join("a.txt");
+        let params = json!({ "to": "bob@example.com", "attachments": [file] });
+        let checked = policy.check("sendMail", Some(&params)).unwrap().unwrap();
+        assert_eq!(
+            checked["attachments"][0],
+            fs::canonicalize(&file).unwrap().to_str().unwrap()
+        );
+        assert_eq!(checked["to"], "bob@example.com");
+
+        let none = json!({ "to": "bob@example.com", "attachments": [] });
+        assert_eq!(policy.check("sendMail", Some(&none)), Ok(None));
+        let key = json!({ "attachments": ["/home/me/.ssh/id_ed25519"] });
+        assert!(policy
+            .check("searchMessages", Some(&key))
+            .unwrap()
+            .is_none());
+        assert!(policy.check("forwardMessage", Some(&key)).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! Append-only JSONL record of the tool calls a [`ThunderbirdClient`] makes,
+//! to see after the fact what an agent did to a mailbox. The CLI records
+//! every command; the bridge records `tools/call` only, not the lookups it
+//! makes itself.
+//!
+//! Each line is one [`AuditEntry`]. Message bodies and event descriptions are
+//! replaced by their length. When the log would grow past its size limit it is renamed to
+//! `<file>.1`, older logs shift up to `<file>.3`, and the oldest is deleted.
+//! Lines are appended with one write each, and writers hold an advisory lock
+//! on `<file>.lock` while they check the size, rotate and append, so the
+//! bridge and the CLI can share a log. On unix the files are created
+//! readable by their owner only, since entries name recipients and subjects.
+//! `AsyncThunderbirdClient` does not write one.
+//!
+//! [`ThunderbirdClient`]: crate::client::ThunderbirdClient
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{check_tool_result, Result, ThunderbirdError};
+use crate::types::{JsonRpcRequest, JsonRpcResponse};
+
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Rotated logs kept besides the current one.
+pub const KEEP_ROTATED: usize = 3;
+
+/// Protocol calls that are not tool calls, and so are not audited.
+const NOT_AUDITED: &[&str] = &["listTools", "getProgress"];
+
+/// Arguments holding message or event text, replaced by their length.
+const REDACTED: &[&str] = &["body", "description"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditStatus {
+    /// The tool ran.
+    Ok,
+    /// The extension answered with an error.
+    Error,
+    /// No answer: Thunderbird was not reachable, timed out, or sent garbage.
+    Failed,
+    /// Not sent: the bridge's tool profile or confirmation fallback forbids it.
+    Refused,
+    /// Not sent: the user did not confirm it when asked.
+    Declined,
+}
+
+impl fmt::Display for AuditStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditStatus::Ok => "ok",
+            AuditStatus::Error => "error",
+            AuditStatus::Failed => "failed",
+            AuditStatus::Refused => "refused",
+            AuditStatus::Declined => "declined",
+        })
+    }
+}
+
+impl FromStr for AuditStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ok" => Ok(AuditStatus::Ok),
+            "error" => Ok(AuditStatus::Error),
+            "failed" => Ok(AuditStatus::Failed),
+            "refused" => Ok(AuditStatus::Refused),
+            "declined" => Ok(AuditStatus::Declined),
+            _ => Err(format!(
+                "invalid status '{}', expected ok, error, failed, refused or declined",
+                s
+            )),
+        }
+    }
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// UTC, e.g. `2026-10-17T09:30:12.345Z`.
+    pub timestamp: String,
+    /// Name of the program that made the call, e.g. `thunderbird-api`.
+    pub caller: String,
+    pub pid: u32,
+    pub tool: String,
+    /// As sent, with message bodies redacted.
+    pub arguments: Value,
+    pub status: AuditStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What `updateMessage` did, e.g. `["marked read", "trashed"]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Value>,
+    pub elapsed_ms: u64,
+}
+
+impl AuditEntry {
+    /// The entry for a call, or `None` for calls that are not tool calls.
+    pub fn new(
+        request: &JsonRpcRequest,
+        result: &Result<JsonRpcResponse>,
+        elapsed: Duration,
+    ) -> Option<Self> {
+        if NOT_AUDITED.contains(&request.method.as_str()) {
+            return None;
+        }
+        let (status, error, actions) = match result {
+            Err(e) => (AuditStatus::Failed, Some(e.to_string()), None),
+            Ok(JsonRpcResponse { error: Some(e), .. }) => {
+                (AuditStatus::Error, Some(e.message.clone()), None)
+            }
+            Ok(JsonRpcResponse {
+                result: Some(result),
+                ..
+            }) => match check_tool_result(result) {
+                Err(e) => (AuditStatus::Error, Some(e.to_string()), None),
+                Ok(()) => (AuditStatus::Ok, None, result.get("actions").cloned()),
+            },
+            Ok(_) => (AuditStatus::Ok, None, None),
+        };
+        Some(Self {
+            timestamp: timestamp(SystemTime::now()),
+            caller: String::new(),
+            pid: std::process::id(),
+            tool: request.method.clone(),
+            arguments: redact(request.params.clone().unwrap_or(json!({}))),
+            status,
+            error,
+            actions,
+            elapsed_ms: elapsed.as_millis() as u64,
+        })
+    }
+
+    /// The entry for a tool call that was not sent, and why.
+    pub fn not_sent(
+        tool: &str,
+        arguments: Option<&Value>,
+        status: AuditStatus,
+        reason: &str,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            timestamp: timestamp(SystemTime::now()),
+            caller: String::new(),
+            pid: std::process::id(),
+            tool: tool.into(),
+            arguments: redact(arguments.cloned().unwrap_or(json!({}))),
+            status,
+            error: Some(reason.into()),
+            actions: None,
+            elapsed_ms: elapsed.as_millis() as u64,
+        }
+    }
+}
+
+/// Filters for [`read`]. Unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    pub tool: Option<String>,
+    pub caller: Option<String>,
+    pub status: Option<AuditStatus>,
+    /// Entries at or after this UTC time, e.g. `2026-10-17` or `2026-10-17T09:00`.
+    pub since: Option<String>,
+    /// Only the most recent matching entries.
+    pub last: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.tool.as_ref().is_none_or(|t| *t == entry.tool)
+            && self.caller.as_ref().is_none_or(|c| *c == entry.caller)
+            && self.status.is_none_or(|s| s == entry.status)
+            // Timestamps of the same format sort as strings
+            && self
+                .since
+                .as_ref()
+                .is_none_or(|s| entry.timestamp.as_str() >= s.as_str())
+    }
+}
+
+/// An audit log file, opened for appending.
+pub struct AuditLog {
+    path: PathBuf,
+    max_size: u64,
+    caller: String,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    /// Open or create the log at `path`, rotating once it reaches `max_size`
+    /// bytes. Entries name the running program as their caller.
+    pub fn open(path: impl Into<PathBuf>, max_size: u64) -> Result<Self> {
+        let path = path.into();
+        append(&path).map_err(|e| audit_error(&path, e))?;
+        let caller = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "unknown".into());
+        Ok(Self {
+            path,
+            max_size,
+            caller,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Name entries with `caller` instead of the program name.
+    pub fn caller(mut self, caller: impl Into<String>) -> Self {
+        self.caller = caller.into();
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, mut entry: AuditEntry) -> Result<()> {
+        entry.caller = self.caller.clone();
+        let mut line = serde_json::to_vec(&entry).map_err(|e| audit_error(&self.path, e))?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        // Other processes writing the same log rotate it too
+        let _shared = lock_shared(&self.path).map_err(|e| audit_error(&self.path, e))?;
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_size {
+            self.rotate().map_err(|e| audit_error(&self.path, e))?;
+        }
+        append(&self.path)
+            .and_then(|mut file| file.write_all(&line))
+            .map_err(|e| audit_error(&self.path, e))
+    }
+
+    fn rotate(&self) -> std::io::Result<()> {
+        let oldest = rotated(&self.path, KEEP_ROTATED);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for n in (1..KEEP_ROTATED).rev() {
+            let from = rotated(&self.path, n);
+            if from.exists() {
+                fs::rename(from, rotated(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))
+    }
+}
+
+/// Entries matching `query` from the log at `path` and its rotated copies,
+/// oldest first. Lines that do not parse, such as one cut short by a crash,
+/// are skipped.
+pub fn read(path: &Path, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+    let mut files: Vec<PathBuf> = (1..=KEEP_ROTATED).rev().map(|n| rotated(path, n)).collect();
+    files.push(path.to_path_buf());
+
+    let mut entries = Vec::new();
+    for file in files {
+        let file = match File::open(&file) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(audit_error(&file, e)),
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| audit_error(path, e))?;
+            if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line) {
+                if query.matches(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+    if let Some(last) = query.last {
+        entries.drain(..entries.len().saturating_sub(last));
+    }
+    Ok(entries)
+}
+
+fn append(path: &Path) -> std::io::Result<File> {
+    create(&mut OpenOptions::new()).append(true).open(path)
+}
+
+/// An exclusive advisory lock on `<path>.lock`, held until the file is dropped.
+fn lock_shared(path: &Path) -> std::io::Result<File> {
+    let file = create(&mut OpenOptions::new())
+        .append(true)
+        .open(lock_path(path))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Create missing files readable and writable by their owner only.
+fn create(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(options, 0o600);
+    options.create(true)
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn redact(mut arguments: Value) -> Value {
+    if let Some(map) = arguments.as_object_mut() {
+        map.remove("_meta");
+        for key in REDACTED {
+            if let Some(Value::String(text)) = map.get(*key) {
+                let redacted = format!("[{} chars redacted]", text.chars().count());
+                map.insert((*key).into(), Value::String(redacted));
+            }
+        }
+    }
+    arguments
+}
+
+/// RFC 3339 in UTC with milliseconds, without a date crate.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rest = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Year, month and day of a day count since 1970-01-01, after Howard
+/// Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn audit_error(path: &Path, e: impl fmt::Display) -> ThunderbirdError {
+    ThunderbirdError::Config(format!("Audit log {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, params: Value) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            id: Some(json!(1)),
+            method: method.into(),
+            params: Some(params),
+        }
+    }
+
+    fn temp_log(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tb-audit-{}-{}", std::process::id(), name));
+        for n in 0..=KEEP_ROTATED {
+            let _ = fs::remove_file(if n == 0 {
+                path.clone()
+            } else {
+                rotated(&path, n)
+            });
+        }
+        path
+    }
+
+    #[test]
+    fn timestamps_are_utc() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_771_511_400_250);
+        assert_eq!(timestamp(time), "2026-02-19T14:30:00.250Z");
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn entries_redact_bodies_and_keep_actions() {
+        let sent = request(
+            "sendMail",
+            json!({ "to": "bob@example.com", "body": "Hi Bob", "_meta": { "progressToken": "1" } }),
+        );
+        let ok = Ok(JsonRpcResponse::success(
+            Some(json!(1)),
+            json!({ "success": true }),
+        ));
+        let entry = AuditEntry::new(&sent, &ok, Duration::from_millis(12)).unwrap();
+        assert_eq!(
+            entry.arguments,
+            json!({ "to": "bob@example.com", "body": "[6 chars redacted]" })
+        );
+        assert_eq!(entry.status, AuditStatus::Ok);
+
+        let event = request(
+            "createEvent",
+            json!({ "title": "Review", "description": "Door code 1234" }),
+        );
+        let entry = AuditEntry::new(&event, &ok, Duration::from_millis(3)).unwrap();
+        assert_eq!(entry.arguments["description"], "[14 chars redacted]");
+
+        let update = request("updateMessage", json!({ "trash": true }));
+        let trashed = Ok(JsonRpcResponse::success(
+            Some(json!(1)),
+            json!({ "success": true, "actions": ["trashed"] }),
+        ));
+        let entry = AuditEntry::new(&update, &trashed, Duration::ZERO).unwrap();
+        assert_eq!(entry.actions, Some(json!(["trashed"])));
+
+        let refused = Ok(JsonRpcResponse::success(
+            Some(json!(1)),
+            json!({ "error": "Message not found" }),
+        ));
+        let entry = AuditEntry::new(&update, &refused, Duration::ZERO).unwrap();
+        assert_eq!(entry.status, AuditStatus::Error);
+
+        assert!(AuditEntry::new(&request("listTools", json!({})), &ok, Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn rotates_and_reads_back_in_order() {
+        let path = temp_log("rotate");
+        let log = AuditLog::open(&path, 600).unwrap().caller("test");
+        let ok = Ok(JsonRpcResponse::success(Some(json!(1)), json!([])));
+        for i in 0..20 {
+            let tool = if i % 2 == 0 {
+                "listFolders"
+            } else {
+                "listAccounts"
+            };
+            let entry = AuditEntry::new(&request(tool, json!({ "n": i })), &ok, Duration::ZERO);
+            log.write(entry.unwrap()).unwrap();
+        }
+        assert!(fs::metadata(&path).unwrap().len() <= 600);
+        assert!(rotated(&path, KEEP_ROTATED).exists());
+        assert!(!rotated(&path, KEEP_ROTATED + 1).exists());
+
+        let query = AuditQuery {
+            tool: Some("listFolders".into()),
+            last: Some(2),
+            ..Default::default()
+        };
+        let entries = read(&path, &query).unwrap();
+        let numbers: Vec<_> = entries.iter().map(|e| e.arguments["n"].clone()).collect();
+        assert_eq!(numbers, [json!(16), json!(18)]);
+        assert_eq!(entries[0].caller, "test");
+
+        for n in 0..=KEEP_ROTATED {
+            let _ = fs::remove_file(if n == 0 {
+                path.clone()
+            } else {
+                rotated(&path, n)
+            });
+        }
+        let _ = fs::remove_file(lock_path(&path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_the_owner_can_read_the_log() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_log("private");
+        AuditLog::open(&path, DEFAULT_MAX_SIZE).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,91 +1,142 @@
-use serde_json::{json, Value};
+use clap::Parser;
+use serde::Serialize;
+use serde_json::Value;
 use std::io::{self, BufRead, Write};
-
-use thunderbird_api::client::ThunderbirdClient;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+use thunderbird_api::cli::ConnectionArgs;
+use thunderbird_api::config::{self, ConfigFile};
+use thunderbird_api::error::{Result, ThunderbirdError};
+use thunderbird_api::mcp::annotations::ToolAnnotations;
+use thunderbird_api::mcp::confirm::ConfirmPolicy;
+use thunderbird_api::mcp::logging::Logger;
+use thunderbird_api::mcp::pool::{WorkerPool, DEFAULT_WORKERS};
+use thunderbird_api::mcp::profile::{Profile, ToolPolicy};
+use thunderbird_api::mcp::prompts::PromptLibrary;
+use thunderbird_api::mcp::subscriptions::DEFAULT_POLL_INTERVAL;
+use thunderbird_api::mcp::{Bridge, Dispatch, Outbox};
+use thunderbird_api::retry::RetryPolicy;
 use thunderbird_api::types::{JsonRpcRequest, JsonRpcResponse};
 
-fn handle_locally(request: &JsonRpcRequest) -> Option<Option<JsonRpcResponse>> {
-    match request.method.as_str() {
-        "initialize" => Some(Some(JsonRpcResponse::success(
-            request.id.clone(),
-            json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": { "tools": {} },
-                "serverInfo": { "name": "thunderbird-bridge", "version": "0.4.0" }
-            }),
-        ))),
-
-        "resources/list" => Some(Some(JsonRpcResponse::success(
-            request.id.clone(),
-            json!({ "resources": [] }),
-        ))),
-
-        "prompts/list" => Some(Some(JsonRpcResponse::success(
-            request.id.clone(),
-            json!({ "prompts": [] }),
-        ))),
-
-        // Notifications produce zero stdout
-        m if m.starts_with("notifications/") => Some(None),
+/// Long enough to ride out a Thunderbird restart without failing the agent's call.
+const DEFAULT_RETRY: Duration = Duration::from_secs(30);
+
+#[derive(Parser)]
+#[command(
+    name = "thunderbird-api",
+    about = "MCP stdio bridge for Thunderbird email",
+    version
+)]
+struct Args {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    /// How often to check subscribed folders for new mail
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_POLL_INTERVAL.as_secs())]
+    poll_secs: u64,
+
+    /// How many calls to Thunderbird can run at the same time
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_WORKERS)]
+    workers: usize,
+
+    /// Tools to offer: readonly, compose, full or custom (default: config
+    /// file, else full)
+    #[arg(long, value_name = "PROFILE")]
+    tool_profile: Option<Profile>,
+
+    /// Also offer this tool; repeat for more
+    #[arg(long, value_name = "TOOL")]
+    allow_tool: Vec<String>,
+
+    /// Never offer this tool; repeat for more
+    #[arg(long, value_name = "TOOL")]
+    deny_tool: Vec<String>,
+}
 
-        _ => None,
+fn config_file(args: &Args) -> Result<ConfigFile> {
+    match args
+        .connection
+        .config
+        .clone()
+        .or_else(config::config_file_path)
+    {
+        Some(path) => ConfigFile::load(&path),
+        None => Ok(ConfigFile::default()),
     }
 }
 
-/// Translate MCP tools/list and tools/call to the extension's direct JSON-RPC protocol.
-fn forward_to_extension(client: &ThunderbirdClient, request: &JsonRpcRequest) -> JsonRpcResponse {
-    match request.method.as_str() {
-        "tools/list" => {
-            let ext_request = JsonRpcRequest {
-                jsonrpc: "2.0".into(),
-                id: request.id.clone(),
-                method: "listTools".into(),
-                params: None,
-            };
-            client.send_raw(&ext_request)
-        }
-        "tools/call" => {
-            let params = request.params.as_ref();
-            let name = params
-                .and_then(|p| p.get("name"))
-                .and_then(|n| n.as_str())
-                .unwrap_or("");
-            let arguments = params
-                .and_then(|p| p.get("arguments"))
-                .cloned()
-                .unwrap_or(json!({}));
-
-            let ext_request = JsonRpcRequest {
-                jsonrpc: "2.0".into(),
-                id: request.id.clone(),
-                method: name.into(),
-                params: Some(arguments),
-            };
-            let ext_response = client.send_raw(&ext_request);
-
-            // Wrap result in MCP content blocks for the MCP client
-            if let Some(result) = ext_response.result {
-                JsonRpcResponse::success(
-                    ext_response.id,
-                    json!({
-                        "content": [{
-                            "type": "text",
-                            "text": serde_json::to_string(&result).unwrap_or_default()
-                        }]
-                    }),
-                )
-            } else {
-                ext_response
-            }
-        }
-        _ => client.send_raw(request),
-    }
+/// Which calls to confirm with the user; unset keys keep their defaults.
+fn confirm_policy(file: &ConfigFile) -> Result<ConfirmPolicy> {
+    let default = ConfirmPolicy::default();
+    let fallback = match &file.confirm_fallback {
+        Some(name) => name.parse().map_err(ThunderbirdError::Config)?,
+        None => default.fallback,
+    };
+    Ok(ConfirmPolicy {
+        destructive: file.confirm_destructive.unwrap_or(default.destructive),
+        attachments: file.confirm_attachments.unwrap_or(default.attachments),
+        fallback,
+    })
+}
+
+/// The profile from the command line, else the config file. Allow and deny
+/// lists from both apply.
+fn tool_policy(args: &Args, file: &ConfigFile) -> Result<ToolPolicy> {
+    let profile = match (args.tool_profile, &file.tool_profile) {
+        (Some(profile), _) => profile,
+        (None, Some(name)) => name.parse().map_err(ThunderbirdError::Config)?,
+        (None, None) => Profile::default(),
+    };
+    let allow = [file.allow_tools.clone(), args.allow_tool.clone()].concat();
+    let deny = [file.deny_tools.clone(), args.deny_tool.clone()].concat();
+    ToolPolicy::new(profile, allow, deny)
 }
 
 fn main() {
-    let client = ThunderbirdClient::new();
+    let args = Args::parse();
+    let outbox = Outbox::new(write_message);
+    let bridge = Logger::from_env(outbox.clone()).and_then(|logger| {
+        let logger = Arc::new(logger);
+        let mut client = args
+            .connection
+            .client_builder()
+            .default_retry(RetryPolicy::with_max_elapsed(DEFAULT_RETRY))
+            .build()?
+            .on_event({
+                let logger = Arc::clone(&logger);
+                move |event| logger.client_event(event)
+            });
+        // Only tool calls are audited, not the bridge's own lookups
+        let audit_log = client.take_audit_log();
+        let prompts = match config::prompts_dir() {
+            Some(dir) => PromptLibrary::load(&dir)?,
+            None => PromptLibrary::default(),
+        };
+        let file = config_file(&args)?;
+        let bridge = Bridge::new(client, outbox)
+            .poll_interval(Duration::from_secs(args.poll_secs.max(1)))
+            .prompts(prompts)
+            .tool_policy(tool_policy(&args, &file)?)
+            .confirm_policy(confirm_policy(&file)?)
+            .tool_annotations(ToolAnnotations::new(file.tool_annotations))
+            .logger(logger);
+        Ok(match audit_log {
+            Some(log) => bridge.audit_log(log),
+            None => bridge,
+        })
+    });
+    let mut bridge = match bridge {
+        Ok(bridge) => bridge,
+        Err(e) => {
+            eprintln!("thunderbird-api: {}", e);
+            process::exit(1);
+        }
+    };
+    // Dropped after the loop, which waits for calls still running
+    let pool = WorkerPool::new(args.workers, write_message);
     let stdin = io::stdin();
-    let stdout = io::stdout();
 
     for line in stdin.lock().lines() {
         let line = match line {
@@ -101,43 +152,46 @@ fn main() {
         }
 
         // Extract the request id before full parse, in case parsing fails
-        let raw_id: Option<Value> = serde_json::from_str::<Value>(&line)
-            .ok()
-            .and_then(|v| v.get("id").cloned());
+        let raw: Option<Value> = serde_json::from_str(&line).ok();
+        let raw_id: Option<Value> = raw.as_ref().and_then(|v| v.get("id").cloned());
+
+        // Answers to the bridge's own requests, such as elicitation/create.
+        // Anything else without a method is a malformed request.
+        if let Some(raw) = raw.filter(|v| v.get("method").is_none() && v.get("id").is_some()) {
+            let delivered = serde_json::from_value::<JsonRpcResponse>(raw)
+                .is_ok_and(|response| bridge.client_response(response));
+            if !delivered {
+                let response = JsonRpcResponse::error(raw_id, -32600, "Invalid Request".into());
+                write_message(&response);
+            }
+            continue;
+        }
 
         let request: JsonRpcRequest = match serde_json::from_str(&line) {
             Ok(r) => r,
             Err(e) => {
-                let response = JsonRpcResponse::error(
-                    raw_id,
-                    -32700,
-                    format!("Parse error: {}", e),
-                );
-                write_response(&stdout, &response);
+                let response =
+                    JsonRpcResponse::error(raw_id, -32700, format!("Parse error: {}", e));
+                write_message(&response);
                 continue;
             }
         };
 
-        // Try handling locally first
-        if let Some(local_result) = handle_locally(&request) {
-            match local_result {
-                Some(response) => write_response(&stdout, &response),
-                None => {
-                    // Notification - produce zero stdout output
-                }
-            }
-            continue;
+        // Notifications produce zero stdout
+        match bridge.dispatch(&request) {
+            Dispatch::Answered(Some(response)) => write_message(&response),
+            Dispatch::Answered(None) => {}
+            Dispatch::Deferred(job) => pool.submit(job),
         }
-
-        // Forward to Thunderbird with MCP-to-direct protocol translation
-        let response = forward_to_extension(&client, &request);
-        write_response(&stdout, &response);
     }
+    bridge.disconnect();
 }
 
-fn write_response(stdout: &io::Stdout, response: &JsonRpcResponse) {
-    let mut handle = stdout.lock();
-    if let Err(e) = serde_json::to_writer(&mut handle, response) {
+/// Write one JSON line. Holding the stdout lock keeps lines from the poller,
+/// the workers and the main loop from interleaving.
+fn write_message(message: &impl Serialize) {
+    let mut handle = io::stdout().lock();
+    if let Err(e) = serde_json::to_writer(&mut handle, message) {
         eprintln!("thunderbird-api: failed to write response: {}", e);
         return;
     }
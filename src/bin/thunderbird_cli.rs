@@ -5,6 +5,6 @@ fn main() {
     let cli = thunderbird_api::cli::Cli::parse();
     if let Err(e) = thunderbird_api::cli::commands::run(cli) {
         eprintln!("Error: {}", e);
-        process::exit(1);
+        process::exit(e.exit_code());
     }
 }
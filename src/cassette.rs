@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{Result, ThunderbirdError, INTERNAL_ERROR};
+use crate::transport::Transport;
+use crate::types::JsonRpcRequest;
+
+/// One line of a JSONL cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub request: Value,
+    /// Response body exactly as the extension sent it, so control characters
+    /// that trigger the sanitize fallback survive the round trip.
+    pub response: String,
+    /// The raw body, only when it was not valid UTF-8; `response` then holds
+    /// a lossy copy for reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_bytes: Option<Vec<u8>>,
+}
+
+impl Exchange {
+    pub fn new(request: Value, body: &[u8]) -> Self {
+        match std::str::from_utf8(body) {
+            Ok(text) => Self {
+                request,
+                response: text.into(),
+                response_bytes: None,
+            },
+            Err(_) => Self {
+                request,
+                response: String::from_utf8_lossy(body).into_owned(),
+                response_bytes: Some(body.to_vec()),
+            },
+        }
+    }
+
+    /// The response body as the extension sent it.
+    pub fn body(&self) -> Vec<u8> {
+        match &self.response_bytes {
+            Some(bytes) => bytes.clone(),
+            None => self.response.clone().into_bytes(),
+        }
+    }
+
+    /// Requests match on method and params; ids and `_meta`, which holds
+    /// per-call progress tokens, are ignored.
+    fn matches(&self, request: &JsonRpcRequest) -> bool {
+        self.request.get("method").and_then(Value::as_str) == Some(request.method.as_str())
+            && without_meta(self.request.get("params").filter(|p| !p.is_null()))
+                == without_meta(request.params.as_ref())
+    }
+}
+
+fn without_meta(params: Option<&Value>) -> Option<Value> {
+    let mut params = params.cloned()?;
+    if let Some(map) = params.as_object_mut() {
+        map.remove("_meta");
+    }
+    Some(params)
+}
+
+/// Passes requests to `inner` and appends every successful exchange to a cassette.
+/// Transport errors are not recorded.
+pub struct RecordingTransport<T> {
+    inner: T,
+    path: PathBuf,
+    out: Mutex<BufWriter<File>>,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    /// Create or truncate the cassette at `path`.
+    pub fn create(path: impl Into<PathBuf>, inner: T) -> Result<Self> {
+        let path = path.into();
+        let file = File::create(&path).map_err(|e| cassette_error(&path, e))?;
+        Ok(Self {
+            inner,
+            path,
+            out: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    fn record(&self, body: &str, response: &[u8]) -> Result<()> {
+        let exchange = Exchange::new(
+            serde_json::from_str(body).unwrap_or(Value::String(body.into())),
+            response,
+        );
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_writer(&mut *out, &exchange).map_err(|e| cassette_error(&self.path, e))?;
+        // Flush per line so a crash still leaves a usable cassette.
+        out.write_all(b"\n")
+            .and_then(|_| out.flush())
+            .map_err(|e| cassette_error(&self.path, e))
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&self, body: &str) -> Result<Vec<u8>> {
+        let response = self.inner.send(body)?;
+        self.record(body, &response)?;
+        Ok(response)
+    }
+
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+}
+
+/// Answers requests from a cassette. Each recorded exchange is used once, in
+/// order; when a request has been asked more often than it was recorded, the
+/// last matching answer is repeated.
+pub struct ReplayTransport {
+    endpoint: String,
+    exchanges: Vec<Exchange>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayTransport {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| cassette_error(path, e))?;
+        let exchanges = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    ThunderbirdError::Config(format!(
+                        "Invalid cassette {} line {}: {}",
+                        path.display(),
+                        i + 1,
+                        e
+                    ))
+                })
+            })
+            .collect::<Result<Vec<Exchange>>>()?;
+        Ok(Self::new(format!("replay:{}", path.display()), exchanges))
+    }
+
+    pub fn new(endpoint: impl Into<String>, exchanges: Vec<Exchange>) -> Self {
+        let used = vec![false; exchanges.len()];
+        Self {
+            endpoint: endpoint.into(),
+            exchanges,
+            used: Mutex::new(used),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, body: &str) -> Result<Vec<u8>> {
+        let request: JsonRpcRequest =
+            serde_json::from_str(body).map_err(|e| ThunderbirdError::Protocol {
+                code: INTERNAL_ERROR,
+                message: format!("Invalid request: {}", e),
+            })?;
+
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        let mut last = None;
+        for (i, exchange) in self.exchanges.iter().enumerate() {
+            if !exchange.matches(&request) {
+                continue;
+            }
+            if !used[i] {
+                used[i] = true;
+                return Ok(exchange.body());
+            }
+            last = Some(exchange);
+        }
+
+        last.map(Exchange::body)
+            .ok_or_else(|| ThunderbirdError::Protocol {
+                code: INTERNAL_ERROR,
+                message: format!(
+                    "No recorded response for {} in {}",
+                    request.method, self.endpoint
+                ),
+            })
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+fn cassette_error(path: &Path, e: impl std::fmt::Display) -> ThunderbirdError {
+    ThunderbirdError::Config(format!("Cassette {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ThunderbirdClient;
+    use crate::retry::RetryPolicy;
+    use crate::transport::MemoryTransport;
+    use serde_json::json;
+
+    fn request(method: &str, params: Value) -> String {
+        serde_json::to_string(
+            &json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = std::env::temp_dir().join(format!("tb-cassette-{}.jsonl", std::process::id()));
+        let raw =
+            "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":[{\"name\":\"Work\",\"type\":\"imap\"}]}";
+
+        let recorder =
+            RecordingTransport::create(&path, MemoryTransport::raw(move |_| Ok(raw.into())))
+                .unwrap();
+        let recorded = ThunderbirdClient::with_transport(recorder, RetryPolicy::none())
+            .list_calendars()
+            .unwrap();
+
+        let replayed = ThunderbirdClient::with_transport(
+            ReplayTransport::open(&path).unwrap(),
+            RetryPolicy::none(),
+        )
+        .list_calendars()
+        .unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(recorded, replayed);
+        assert_eq!(replayed[0].name, "Work");
+    }
+
+    #[test]
+    fn replays_in_order_then_repeats_last() {
+        let replay = ReplayTransport::new(
+            "test",
+            vec![
+                Exchange::new(json!({ "method": "listFolders", "params": {} }), b"first"),
+                Exchange::new(json!({ "method": "listFolders", "params": {} }), b"second"),
+            ],
+        );
+        let body = request("listFolders", json!({}));
+        assert_eq!(replay.send(&body).unwrap(), b"first");
+        assert_eq!(replay.send(&body).unwrap(), b"second");
+        assert_eq!(replay.send(&body).unwrap(), b"second");
+    }
+
+    #[test]
+    fn invalid_utf8_round_trips() {
+        let body = b"{\"result\":\"caf\xe9\"}";
+        let exchange = Exchange::new(json!({ "method": "getMessage" }), body);
+        let line = serde_json::to_string(&exchange).unwrap();
+        let read: Exchange = serde_json::from_str(&line).unwrap();
+        assert_eq!(read.response, "{\"result\":\"caf\u{FFFD}\"}");
+        assert_eq!(read.body(), body);
+    }
+
+    #[test]
+    fn progress_tokens_are_ignored() {
+        let replay = ReplayTransport::new(
+            "test",
+            vec![Exchange::new(
+                json!({
+                    "method": "searchMessages",
+                    "params": { "query": "a", "_meta": { "progressToken": "1-1" } }
+                }),
+                b"[]",
+            )],
+        );
+        let body = request(
+            "searchMessages",
+            json!({ "query": "a", "_meta": { "progressToken": "2-7" } }),
+        );
+        assert_eq!(replay.send(&body).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn unrecorded_request_is_an_error() {
+        let replay = ReplayTransport::new(
+            "test",
+            vec![Exchange::new(
+                json!({ "method": "getMessage", "params": { "messageId": "a" } }),
+                b"{}",
+            )],
+        );
+        let err = replay
+            .send(&request("getMessage", json!({ "messageId": "b" })))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No recorded response for getMessage in test"
+        );
+    }
+}
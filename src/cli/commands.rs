@@ -1,18 +1,25 @@
-use anyhow::Result;
-use serde_json::{json, Value};
-
+use crate::audit::{self, AuditQuery};
 use crate::client::ThunderbirdClient;
+use crate::error::{Result, ThunderbirdError};
+use crate::model::{
+    ForwardArgs, GetMessageArgs, ListFoldersArgs, ReplyArgs, SearchArgs, SearchContactsArgs,
+    SendMailArgs, UpdateMessageArgs,
+};
 
 use super::format;
 use super::{Cli, Command};
 
 pub fn run(cli: Cli) -> Result<()> {
-    let client = ThunderbirdClient::new();
+    let client = cli.connection.client_builder().build()?;
+    execute(&client, cli.command)
+}
 
-    match cli.command {
+/// Run one subcommand against `client`, printing the result to stdout.
+pub fn execute(client: &ThunderbirdClient, command: Command) -> Result<()> {
+    match command {
         Command::Accounts => {
-            let result = client.call_tool("listAccounts", json!({}))?;
-            format::print_accounts(&result);
+            let accounts = client.list_accounts()?;
+            format::print_accounts(&accounts);
         }
 
         Command::Search {
@@ -22,23 +29,14 @@ pub fn run(cli: Cli) -> Result<()> {
             max,
             sort,
         } => {
-            let mut args = json!({ "query": query });
-            let obj = args.as_object_mut().unwrap();
-            if let Some(sd) = start_date {
-                obj.insert("startDate".into(), json!(sd));
-            }
-            if let Some(ed) = end_date {
-                obj.insert("endDate".into(), json!(ed));
-            }
-            if let Some(m) = max {
-                obj.insert("maxResults".into(), json!(m));
-            }
-            if let Some(s) = sort {
-                obj.insert("sortOrder".into(), json!(s));
-            }
-            let result = client.call_tool("searchMessages", args)?;
-            check_error(&result)?;
-            format::print_messages(&result);
+            let messages = client.search_messages(&SearchArgs {
+                query,
+                start_date,
+                end_date,
+                max_results: max,
+                sort_order: sort,
+            })?;
+            format::print_messages(&messages);
         }
 
         Command::Get {
@@ -46,27 +44,19 @@ pub fn run(cli: Cli) -> Result<()> {
             folder_path,
             save_attachments,
         } => {
-            let result = client.call_tool(
-                "getMessage",
-                json!({
-                    "messageId": message_id,
-                    "folderPath": folder_path,
-                    "saveAttachments": save_attachments,
-                }),
-            )?;
-            format::print_message(&result);
+            let message = client.get_message(&GetMessageArgs {
+                message_id,
+                folder_path,
+                save_attachments,
+            })?;
+            format::print_message(&message);
         }
 
         Command::Folders { account } => {
-            let mut args = json!({});
-            if let Some(a) = account {
-                args.as_object_mut()
-                    .unwrap()
-                    .insert("accountId".into(), json!(a));
-            }
-            let result = client.call_tool("listFolders", args)?;
-            check_error(&result)?;
-            format::print_folders(&result);
+            let folders = client.list_folders(&ListFoldersArgs {
+                account_id: account,
+            })?;
+            format::print_folders(&folders);
         }
 
         Command::Update {
@@ -79,38 +69,29 @@ pub fn run(cli: Cli) -> Result<()> {
             move_to,
             trash,
         } => {
-            let mut args = json!({
-                "messageId": message_id,
-                "folderPath": folder_path,
-            });
-            let obj = args.as_object_mut().unwrap();
-            if read {
-                obj.insert("read".into(), json!(true));
-            }
-            if unread {
-                obj.insert("read".into(), json!(false));
-            }
-            if flag {
-                obj.insert("flagged".into(), json!(true));
-            }
-            if unflag {
-                obj.insert("flagged".into(), json!(false));
-            }
-            if let Some(m) = move_to {
-                obj.insert("moveTo".into(), json!(m));
-            }
-            if trash {
-                obj.insert("trash".into(), json!(true));
-            }
-            let result = client.call_tool("updateMessage", args)?;
-            check_error(&result)?;
-            if let Some(actions) = result.get("actions").and_then(|v| v.as_array()) {
-                let action_strs: Vec<&str> = actions
-                    .iter()
-                    .filter_map(|a| a.as_str())
-                    .collect();
-                println!("Done: {}", action_strs.join(", "));
-            }
+            let read = if read {
+                Some(true)
+            } else if unread {
+                Some(false)
+            } else {
+                None
+            };
+            let flagged = if flag {
+                Some(true)
+            } else if unflag {
+                Some(false)
+            } else {
+                None
+            };
+            let result = client.update_message(&UpdateMessageArgs {
+                message_id,
+                folder_path,
+                read,
+                flagged,
+                move_to,
+                trash: trash.then_some(true),
+            })?;
+            format::print_update(&result);
         }
 
         Command::Send {
@@ -123,32 +104,17 @@ pub fn run(cli: Cli) -> Result<()> {
             html,
             attachments,
         } => {
-            let mut args = json!({
-                "to": to,
-                "subject": subject.unwrap_or_default(),
-                "body": body.unwrap_or_default(),
-                "isHtml": html,
-            });
-            let obj = args.as_object_mut().unwrap();
-            if let Some(cc_val) = cc {
-                obj.insert("cc".into(), json!(cc_val));
-            }
-            if let Some(bcc_val) = bcc {
-                obj.insert("bcc".into(), json!(bcc_val));
-            }
-            if let Some(from_val) = from {
-                obj.insert("from".into(), json!(from_val));
-            }
-            if !attachments.is_empty() {
-                obj.insert("attachments".into(), json!(attachments));
-            }
-            let result = client.call_tool("sendMail", args)?;
-            check_error(&result)?;
-            let msg = result
-                .get("message")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Compose window opened.");
-            println!("{}", msg);
+            let result = client.send_mail(&SendMailArgs {
+                to,
+                subject: subject.unwrap_or_default(),
+                body: body.unwrap_or_default(),
+                cc,
+                bcc,
+                from,
+                is_html: html,
+                attachments: absolute(attachments),
+            })?;
+            format::print_compose(&result, "Compose window opened.");
         }
 
         Command::Reply {
@@ -162,33 +128,19 @@ pub fn run(cli: Cli) -> Result<()> {
             from,
             attachments,
         } => {
-            let mut args = json!({
-                "messageId": message_id,
-                "folderPath": folder_path,
-                "body": body,
-                "replyAll": reply_all,
-                "isHtml": html,
-            });
-            let obj = args.as_object_mut().unwrap();
-            if let Some(to_val) = to {
-                obj.insert("to".into(), json!(to_val));
-            }
-            if let Some(cc_val) = cc {
-                obj.insert("cc".into(), json!(cc_val));
-            }
-            if let Some(from_val) = from {
-                obj.insert("from".into(), json!(from_val));
-            }
-            if !attachments.is_empty() {
-                obj.insert("attachments".into(), json!(attachments));
-            }
-            let result = client.call_tool("replyToMessage", args)?;
-            check_error(&result)?;
-            let msg = result
-                .get("message")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Reply compose window opened.");
-            println!("{}", msg);
+            let result = client.reply_to_message(&ReplyArgs {
+                message_id,
+                folder_path,
+                body,
+                reply_all,
+                is_html: html,
+                to,
+                cc,
+                bcc: None,
+                from,
+                attachments: absolute(attachments),
+            })?;
+            format::print_compose(&result, "Reply compose window opened.");
         }
 
         Command::Forward {
@@ -201,53 +153,72 @@ pub fn run(cli: Cli) -> Result<()> {
             from,
             attachments,
         } => {
-            let mut args = json!({
-                "messageId": message_id,
-                "folderPath": folder_path,
-                "to": to,
-                "isHtml": html,
-            });
-            let obj = args.as_object_mut().unwrap();
-            if let Some(body_val) = body {
-                obj.insert("body".into(), json!(body_val));
-            }
-            if let Some(cc_val) = cc {
-                obj.insert("cc".into(), json!(cc_val));
-            }
-            if let Some(from_val) = from {
-                obj.insert("from".into(), json!(from_val));
-            }
-            if !attachments.is_empty() {
-                obj.insert("attachments".into(), json!(attachments));
-            }
-            let result = client.call_tool("forwardMessage", args)?;
-            check_error(&result)?;
-            let msg = result
-                .get("message")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Forward compose window opened.");
-            println!("{}", msg);
+            let result = client.forward_message(&ForwardArgs {
+                message_id,
+                folder_path,
+                to,
+                body,
+                is_html: html,
+                cc,
+                bcc: None,
+                from,
+                attachments: absolute(attachments),
+            })?;
+            format::print_compose(&result, "Forward compose window opened.");
         }
 
         Command::Contacts { query } => {
-            let result = client.call_tool("searchContacts", json!({ "query": query }))?;
-            check_error(&result)?;
-            format::print_contacts(&result);
+            let contacts = client.search_contacts(&SearchContactsArgs { query })?;
+            format::print_contacts(&contacts);
         }
 
         Command::Calendars => {
-            let result = client.call_tool("listCalendars", json!({}))?;
-            check_error(&result)?;
-            format::print_calendars(&result);
+            let calendars = client.list_calendars()?;
+            format::print_calendars(&calendars);
+        }
+
+        Command::Audit {
+            tool,
+            caller,
+            status,
+            since,
+            last,
+            json,
+        } => {
+            let log = client.audit_log().ok_or_else(|| {
+                ThunderbirdError::Config(
+                    "No audit log: pass --audit-log or set audit_log in the config file".into(),
+                )
+            })?;
+            let query = AuditQuery {
+                tool,
+                caller,
+                status,
+                since,
+                last,
+            };
+            let entries = audit::read(log.path(), &query)?;
+            if json {
+                for entry in &entries {
+                    println!("{}", serde_json::to_string(entry).unwrap_or_default());
+                }
+            } else {
+                format::print_audit(&entries);
+            }
         }
     }
 
     Ok(())
 }
 
-fn check_error(result: &Value) -> Result<()> {
-    if let Some(err) = result.get("error").and_then(|v| v.as_str()) {
-        anyhow::bail!("{}", err);
-    }
-    Ok(())
+/// `--attach` paths made absolute against the user's current directory,
+/// since the attachment check refuses relative ones.
+fn absolute(paths: Vec<String>) -> Vec<String> {
+    paths
+        .into_iter()
+        .map(|path| match std::path::absolute(&path) {
+            Ok(absolute) => absolute.to_string_lossy().into_owned(),
+            Err(_) => path,
+        })
+        .collect()
 }
@@ -1,8 +1,22 @@
-use serde_json::Value;
+use std::fmt::Write;
+
+use crate::audit::AuditEntry;
+use crate::model::{
+    Account, Calendar, ComposeResult, Contact, Folder, Message, MessageSummary, UpdateResult,
+};
+
+/// `writeln!` into a `String`, which cannot fail.
+macro_rules! emit {
+    ($out:expr) => {
+        $out.push('\n')
+    };
+    ($out:expr, $($arg:tt)*) => {{
+        let _ = writeln!($out, $($arg)*);
+    }};
+}
 
 const MONTHS: [&str; 12] = [
-    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
-    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
 ];
 
 /// Format an ISO 8601 date string as "19 Feb 2026 14:30".
@@ -47,245 +61,288 @@ pub fn truncate(s: &str, max_len: usize) -> String {
     }
 }
 
-pub fn print_messages(messages: &Value) {
-    let arr = match messages.as_array() {
-        Some(a) if !a.is_empty() => a,
-        _ => {
-            println!("No messages found.");
-            return;
-        }
-    };
+pub fn render_messages(messages: &[MessageSummary]) -> String {
+    let mut out = String::new();
+    if messages.is_empty() {
+        emit!(out, "No messages found.");
+        return out;
+    }
 
-    for msg in arr {
-        let flags = build_flags(msg);
+    for msg in messages {
+        let flags = build_flags(msg.read, msg.flagged);
         let flag_str = if flags.is_empty() {
             String::new()
         } else {
             format!(" [{}]", flags)
         };
 
-        let author = msg
-            .get("author")
-            .or_else(|| msg.get("from"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        let date = msg.get("date").and_then(|v| v.as_str()).unwrap_or("");
-        let subject = msg
-            .get("subject")
-            .and_then(|v| v.as_str())
-            .unwrap_or("(no subject)");
-        let id = msg.get("id").and_then(|v| v.as_str()).unwrap_or("");
-        let folder = msg
-            .get("folderPath")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-
-        println!("{}  {}", format_date(date), truncate(author, 30));
-        println!("  {}{}", subject, flag_str);
-        println!("  id: {}  folder: {}", id, folder);
-        println!();
+        emit!(
+            out,
+            "{}  {}",
+            format_date(&msg.date),
+            truncate(&msg.author, 30)
+        );
+        emit!(out, "  {}{}", or_no_subject(&msg.subject), flag_str);
+        emit!(out, "  id: {}  folder: {}", msg.id, msg.folder_path);
+        emit!(out);
     }
 
-    println!("{} message(s)", arr.len());
+    emit!(out, "{} message(s)", messages.len());
+    out
 }
 
-pub fn print_message(msg: &Value) {
-    if let Some(err) = msg.get("error").and_then(|v| v.as_str()) {
-        eprintln!("Error: {}", err);
-        std::process::exit(1);
-    }
+pub fn print_messages(messages: &[MessageSummary]) {
+    print!("{}", render_messages(messages));
+}
 
-    let flags = build_flags(msg);
+pub fn render_message(msg: &Message) -> String {
+    let mut out = String::new();
+    let flags = build_flags(msg.read, msg.flagged);
     let flag_str = if flags.is_empty() {
         String::new()
     } else {
         format!(" [{}]", flags)
     };
 
-    let subject = msg
-        .get("subject")
-        .and_then(|v| v.as_str())
-        .unwrap_or("(no subject)");
-    let author = msg.get("author").and_then(|v| v.as_str()).unwrap_or("");
-    let recipients = msg
-        .get("recipients")
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-    let cc = msg.get("ccList").and_then(|v| v.as_str());
-    let date = msg.get("date").and_then(|v| v.as_str()).unwrap_or("");
-    let id = msg.get("id").and_then(|v| v.as_str()).unwrap_or("");
-
-    println!("Subject: {}{}", subject, flag_str);
-    println!("From:    {}", author);
-    println!("To:      {}", recipients);
-    if let Some(cc_val) = cc {
-        println!("CC:      {}", cc_val);
+    emit!(out, "Subject: {}{}", or_no_subject(&msg.subject), flag_str);
+    emit!(out, "From:    {}", msg.author);
+    emit!(out, "To:      {}", msg.recipients);
+    if !msg.cc_list.is_empty() {
+        emit!(out, "CC:      {}", msg.cc_list);
     }
-    println!("Date:    {}", format_date(date));
-    println!("ID:      {}", id);
-
-    if let Some(attachments) = msg.get("attachments").and_then(|v| v.as_array()) {
-        if !attachments.is_empty() {
-            println!("\nAttachments ({}):", attachments.len());
-            for att in attachments {
-                let name = att.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
-                let size_str = att
-                    .get("size")
-                    .and_then(|v| v.as_f64())
-                    .map(|s| format!(" ({:.1}KB)", s / 1024.0))
-                    .unwrap_or_default();
-                let path_str = att
-                    .get("filePath")
-                    .and_then(|v| v.as_str())
-                    .map(|p| format!(" -> {}", p))
-                    .unwrap_or_default();
-                let err_str = att
-                    .get("error")
-                    .and_then(|v| v.as_str())
-                    .map(|e| format!(" [{}]", e))
-                    .unwrap_or_default();
-                println!("  {}{}{}{}", name, size_str, path_str, err_str);
-            }
+    emit!(out, "Date:    {}", format_date(&msg.date));
+    emit!(out, "ID:      {}", msg.id);
+
+    if !msg.attachments.is_empty() {
+        emit!(out, "\nAttachments ({}):", msg.attachments.len());
+        for att in &msg.attachments {
+            let name = if att.name.is_empty() {
+                "unknown"
+            } else {
+                &att.name
+            };
+            let size_str = format!(" ({:.1}KB)", att.size as f64 / 1024.0);
+            let path_str = att
+                .file_path
+                .as_ref()
+                .map(|p| format!(" -> {}", p))
+                .unwrap_or_default();
+            let err_str = att
+                .error
+                .as_ref()
+                .map(|e| format!(" [{}]", e))
+                .unwrap_or_default();
+            emit!(out, "  {}{}{}{}", name, size_str, path_str, err_str);
         }
     }
 
-    let body = msg
-        .get("body")
-        .and_then(|v| v.as_str())
-        .unwrap_or("(empty body)");
-    println!("\n{}", body);
+    let body = if msg.body.is_empty() {
+        "(empty body)"
+    } else {
+        &msg.body
+    };
+    emit!(out, "\n{}", body);
+    out
 }
 
-pub fn print_folders(folders: &Value) {
-    let arr = match folders.as_array() {
-        Some(a) if !a.is_empty() => a,
-        _ => {
-            println!("No folders found.");
-            return;
-        }
-    };
+pub fn print_message(msg: &Message) {
+    print!("{}", render_message(msg));
+}
 
-    for f in arr {
-        let depth = f.get("depth").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
-        let indent = "  ".repeat(depth);
-        let name = f.get("name").and_then(|v| v.as_str()).unwrap_or("");
-        let total = f
-            .get("totalMessages")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0);
-        let unread = f
-            .get("unreadMessages")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0);
-        let path = f.get("path").and_then(|v| v.as_str()).unwrap_or("");
-
-        let unread_str = if unread > 0 {
-            format!(" ({} unread)", unread)
+pub fn render_folders(folders: &[Folder]) -> String {
+    let mut out = String::new();
+    if folders.is_empty() {
+        emit!(out, "No folders found.");
+        return out;
+    }
+
+    for f in folders {
+        let indent = "  ".repeat(f.depth);
+
+        let unread_str = if f.unread_messages > 0 {
+            format!(" ({} unread)", f.unread_messages)
         } else {
             String::new()
         };
 
-        println!("{}{}  [{} msgs{}]", indent, name, total, unread_str);
-        println!("{}  {}", indent, path);
+        emit!(
+            out,
+            "{}{}  [{} msgs{}]",
+            indent,
+            f.name,
+            f.total_messages,
+            unread_str
+        );
+        emit!(out, "{}  {}", indent, f.path);
     }
+    out
 }
 
-pub fn print_accounts(accounts: &Value) {
-    let arr = match accounts.as_array() {
-        Some(a) if !a.is_empty() => a,
-        _ => {
-            println!("No accounts found.");
-            return;
-        }
-    };
+pub fn print_folders(folders: &[Folder]) {
+    print!("{}", render_folders(folders));
+}
+
+pub fn render_accounts(accounts: &[Account]) -> String {
+    let mut out = String::new();
+    if accounts.is_empty() {
+        emit!(out, "No accounts found.");
+        return out;
+    }
 
-    for acc in arr {
-        let name = acc
-            .get("name")
-            .or_else(|| acc.get("key"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-        let acc_type = acc.get("type").and_then(|v| v.as_str()).unwrap_or("");
-        println!("{} ({})", name, acc_type);
-
-        if let Some(identities) = acc.get("identities").and_then(|v| v.as_array()) {
-            for id in identities {
-                let id_name = id.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                let email = id.get("email").and_then(|v| v.as_str()).unwrap_or("");
-                println!("  {} <{}>", id_name, email);
-            }
+    for acc in accounts {
+        emit!(out, "{} ({})", acc.name, acc.account_type);
+        for id in &acc.identities {
+            emit!(out, "  {} <{}>", id.name, id.email);
         }
-        println!();
+        emit!(out);
     }
+    out
 }
 
-pub fn print_contacts(contacts: &Value) {
-    let arr = match contacts.as_array() {
-        Some(a) if !a.is_empty() => a,
-        _ => {
-            println!("No contacts found.");
-            return;
-        }
-    };
+pub fn print_accounts(accounts: &[Account]) {
+    print!("{}", render_accounts(accounts));
+}
 
-    for c in arr {
-        let first = c.get("firstName").and_then(|v| v.as_str()).unwrap_or("");
-        let last = c.get("lastName").and_then(|v| v.as_str()).unwrap_or("");
-        let display = c.get("displayName").and_then(|v| v.as_str()).unwrap_or("");
+pub fn render_contacts(contacts: &[Contact]) -> String {
+    let mut out = String::new();
+    if contacts.is_empty() {
+        emit!(out, "No contacts found.");
+        return out;
+    }
 
-        let name = if !first.is_empty() || !last.is_empty() {
-            [first, last]
+    for c in contacts {
+        let name = if !c.first_name.is_empty() || !c.last_name.is_empty() {
+            [c.first_name.as_str(), c.last_name.as_str()]
                 .iter()
                 .filter(|s| !s.is_empty())
                 .copied()
                 .collect::<Vec<_>>()
                 .join(" ")
         } else {
-            display.to_string()
+            c.display_name.clone()
         };
 
-        // Extension returns "email", not "primaryEmail"
-        let email = c
-            .get("email")
-            .or_else(|| c.get("primaryEmail"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
+        emit!(out, "{}  <{}>", name, c.email);
+    }
+
+    emit!(out, "\n{} contact(s)", contacts.len());
+    out
+}
 
-        println!("{}  <{}>", name, email);
+pub fn print_contacts(contacts: &[Contact]) {
+    print!("{}", render_contacts(contacts));
+}
+
+pub fn render_calendars(calendars: &[Calendar]) -> String {
+    let mut out = String::new();
+    if calendars.is_empty() {
+        emit!(out, "No calendars found.");
+        return out;
+    }
+
+    for cal in calendars {
+        let cal_type = if cal.calendar_type.is_empty() {
+            "unknown"
+        } else {
+            &cal.calendar_type
+        };
+        emit!(out, "{} ({})", cal.name, cal_type);
+        if let Some(color) = &cal.color {
+            emit!(out, "  color: {}", color);
+        }
     }
+    out
+}
+
+pub fn print_calendars(calendars: &[Calendar]) {
+    print!("{}", render_calendars(calendars));
+}
 
-    println!("\n{} contact(s)", arr.len());
+/// "Done: marked read, trashed", or nothing if no action was taken.
+pub fn render_update(result: &UpdateResult) -> String {
+    let mut out = String::new();
+    if !result.actions.is_empty() {
+        emit!(out, "Done: {}", result.actions.join(", "));
+    }
+    out
 }
 
-pub fn print_calendars(calendars: &Value) {
-    let arr = match calendars.as_array() {
-        Some(a) if !a.is_empty() => a,
-        _ => {
-            println!("No calendars found.");
-            return;
+pub fn print_update(result: &UpdateResult) {
+    print!("{}", render_update(result));
+}
+
+/// One line per call: time, caller, tool, status and latency, then the
+/// arguments and what came of the call.
+pub fn render_audit(entries: &[AuditEntry]) -> String {
+    let mut out = String::new();
+    if entries.is_empty() {
+        emit!(out, "No audited calls found.");
+        return out;
+    }
+
+    for entry in entries {
+        // 2026-10-17T09:30:12.345Z -> 2026-10-17 09:30:12
+        let time: String = entry.timestamp.replace('T', " ").chars().take(19).collect();
+        emit!(
+            out,
+            "{}  {}  {}  {}  {} ms",
+            time,
+            entry.caller,
+            entry.tool,
+            entry.status,
+            entry.elapsed_ms
+        );
+        emit!(out, "  {}", truncate(&entry.arguments.to_string(), 100));
+        if let Some(error) = &entry.error {
+            emit!(out, "  error: {}", error);
+        }
+        let actions: Vec<_> = entry
+            .actions
+            .as_ref()
+            .and_then(|a| a.as_array())
+            .map(|a| a.iter().filter_map(|a| a.as_str()).collect())
+            .unwrap_or_default();
+        if !actions.is_empty() {
+            emit!(out, "  done: {}", actions.join(", "));
         }
+    }
+
+    emit!(out, "{} call(s)", entries.len());
+    out
+}
+
+pub fn print_audit(entries: &[AuditEntry]) {
+    print!("{}", render_audit(entries));
+}
+
+/// The extension's message for a compose tool, or `fallback` if it sent none.
+pub fn render_compose(result: &ComposeResult, fallback: &str) -> String {
+    let message = if result.message.is_empty() {
+        fallback
+    } else {
+        &result.message
     };
+    format!("{}\n", message)
+}
 
-    for cal in arr {
-        let name = cal.get("name").and_then(|v| v.as_str()).unwrap_or("");
-        let cal_type = cal
-            .get("type")
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown");
-        println!("{} ({})", name, cal_type);
-        if let Some(color) = cal.get("color").and_then(|v| v.as_str()) {
-            println!("  color: {}", color);
-        }
+pub fn print_compose(result: &ComposeResult, fallback: &str) {
+    print!("{}", render_compose(result, fallback));
+}
+
+fn or_no_subject(subject: &str) -> &str {
+    if subject.is_empty() {
+        "(no subject)"
+    } else {
+        subject
     }
 }
 
-fn build_flags(msg: &Value) -> String {
+fn build_flags(read: bool, flagged: bool) -> String {
     let mut parts = Vec::new();
-    if msg.get("read") == Some(&Value::Bool(false)) {
+    if !read {
         parts.push("UNREAD");
     }
-    if msg.get("flagged") == Some(&Value::Bool(true)) {
+    if flagged {
         parts.push("FLAGGED");
     }
     parts.join(" ")
@@ -1,19 +1,106 @@
 pub mod commands;
 pub mod format;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::audit::AuditStatus;
+use crate::client::ClientBuilder;
+use crate::model::SortOrder;
+use crate::retry::RetryPolicy;
+
+/// Keep in sync with `ThunderbirdError::exit_code` and the README.
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  other error
+  2  invalid command-line usage
+  3  Thunderbird is not running (connection refused)
+  4  request timed out
+  5  protocol error or invalid response from the extension
+  6  the tool reported an error
+  7  message, folder or calendar not found
+  8  invalid configuration
+  9  the extension rejected the API token
+  10 invalid tool arguments";
 
 #[derive(Parser)]
 #[command(
     name = "thunderbird-cli",
     about = "Command-line interface for Thunderbird email",
-    version
+    version,
+    after_help = EXIT_CODES
 )]
 pub struct Cli {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Endpoint overrides shared by thunderbird-cli and the thunderbird-api bridge.
+#[derive(Args, Debug, Default)]
+pub struct ConnectionArgs {
+    /// Extension endpoint URL (e.g. http://localhost:8756/)
+    #[arg(long, global = true)]
+    pub endpoint: Option<String>,
+
+    /// Extension port on localhost
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+
+    /// Config file (default: $XDG_CONFIG_HOME/thunderbird-api/config.toml)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Keep retrying read-only calls for this many seconds while Thunderbird
+    /// is unreachable (0 disables)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub retry_secs: Option<u64>,
+
+    /// Record every request and response to a JSONL cassette
+    #[arg(long, global = true, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Answer requests from a recorded cassette instead of Thunderbird
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    /// Append every tool call to a JSONL audit log
+    #[arg(long, global = true, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+}
+
+impl ConnectionArgs {
+    pub fn client_builder(&self) -> ClientBuilder {
+        let mut builder = ClientBuilder::new();
+        if let Some(endpoint) = &self.endpoint {
+            builder = builder.endpoint(endpoint);
+        }
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some(config) = &self.config {
+            builder = builder.config_file(config);
+        }
+        if let Some(secs) = self.retry_secs {
+            builder = builder.retry(RetryPolicy::with_max_elapsed(Duration::from_secs(secs)));
+        }
+        if let Some(path) = &self.record {
+            builder = builder.record(path);
+        }
+        if let Some(path) = &self.replay {
+            builder = builder.replay(path);
+        }
+        if let Some(path) = &self.audit_log {
+            builder = builder.audit_log(path);
+        }
+        builder
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Command {
     /// List email accounts and identities
@@ -38,7 +125,7 @@ pub enum Command {
 
         /// Sort order (asc or desc, default: desc)
         #[arg(long)]
-        sort: Option<String>,
+        sort: Option<SortOrder>,
     },
 
     /// Read a full email message
@@ -207,4 +294,31 @@ pub enum Command {
 
     /// List calendars
     Calendars,
+
+    /// Show tool calls from the audit log (--audit-log or audit_log in the config file)
+    Audit {
+        /// Only calls to this tool
+        #[arg(long)]
+        tool: Option<String>,
+
+        /// Only calls made by this program, e.g. thunderbird-api
+        #[arg(long)]
+        caller: Option<String>,
+
+        /// Only calls with this status (ok, error, failed, refused or declined)
+        #[arg(long)]
+        status: Option<AuditStatus>,
+
+        /// Only calls at or after this UTC time (ISO 8601)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only the last N matching calls
+        #[arg(long, value_name = "N")]
+        last: Option<usize>,
+
+        /// Print entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
 }
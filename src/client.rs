@@ -1,16 +1,213 @@
-use anyhow::{Context, Result};
-use serde_json::Value;
-use std::time::Duration;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use crate::sanitize::sanitize_json;
+use crate::attachments::AttachmentPolicy;
+use crate::audit::{AuditEntry, AuditLog};
+use crate::cassette::{RecordingTransport, ReplayTransport};
+use crate::config::{ClientConfig, EndpointSource};
+use crate::error::{check_tool_result, Result, ThunderbirdError, INTERNAL_ERROR};
+use crate::model::{
+    Account, Calendar, ComposeResult, Contact, CreateEventArgs, Folder, ForwardArgs,
+    GetMessageArgs, ListFoldersArgs, Message, MessageSummary, Progress, ReplyArgs, SearchArgs,
+    SearchContactsArgs, SendMailArgs, UpdateMessageArgs, UpdateResult,
+};
+use crate::retry::RetryPolicy;
+use crate::sanitize::{decode_lossy, sanitize_json};
+use crate::transport::{HttpTransport, Transport};
 use crate::types::{JsonRpcRequest, JsonRpcResponse};
 
-const THUNDERBIRD_PORT: u16 = 8756;
-const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
-
 pub struct ThunderbirdClient {
-    agent: ureq::Agent,
-    url: String,
+    transport: Box<dyn Transport>,
+    retry: RetryPolicy,
+    observer: Option<Observer>,
+    audit: Option<AuditLog>,
+    attachments: AttachmentPolicy,
+}
+
+type Observer = Box<dyn Fn(&ClientEvent) + Send + Sync>;
+
+/// Something that happened while talking to the extension, for logging.
+/// See [`ThunderbirdClient::on_event`].
+#[derive(Debug)]
+pub enum ClientEvent<'a> {
+    /// A read-only call could not reach Thunderbird and is tried again after `delay`.
+    Retrying {
+        method: &'a str,
+        delay: Duration,
+        error: &'a ThunderbirdError,
+    },
+    /// The response was not valid JSON as sent and was repaired. `lossy` if
+    /// invalid UTF-8 was replaced, rather than only control characters removed.
+    Sanitized { method: &'a str, lossy: bool },
+    /// The call failed without a response from the extension.
+    Failed {
+        method: &'a str,
+        error: &'a ThunderbirdError,
+    },
+    /// A response arrived, `elapsed` after the call started, retries included.
+    Completed { method: &'a str, elapsed: Duration },
+    /// The call could not be written to the audit log.
+    AuditFailed {
+        method: &'a str,
+        error: &'a ThunderbirdError,
+    },
+}
+
+/// Builds a `ThunderbirdClient`, discovering the endpoint for anything not set
+/// explicitly. See [`ClientConfig::discover`] for the lookup order.
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    explicit: EndpointSource,
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    default_retry: RetryPolicy,
+    token: Option<String>,
+    max_response_size: Option<u64>,
+    config_path: Option<PathBuf>,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    audit_log: Option<PathBuf>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Full endpoint URL. Takes precedence over `port`.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.explicit.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Port on localhost.
+    pub fn port(mut self, port: u16) -> Self {
+        self.explicit.port = Some(port);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry policy for read-only tools. Overrides `retry_secs` in the config file.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Retry policy used when neither [`ClientBuilder::retry`] nor the config
+    /// file sets one. Defaults to [`RetryPolicy::none`].
+    pub fn default_retry(mut self, retry: RetryPolicy) -> Self {
+        self.default_retry = retry;
+        self
+    }
+
+    /// Bearer token. Overrides `THUNDERBIRD_API_TOKEN` and the token file.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Largest response body accepted, in bytes. Overrides `max_response_mb`
+    /// in the config file.
+    pub fn max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Read this config file instead of the default location.
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Write every request/response pair to a JSONL cassette at `path`.
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record = Some(path.into());
+        self
+    }
+
+    /// Answer requests from a cassette instead of Thunderbird. Wins over `record`.
+    pub fn replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.replay = Some(path.into());
+        self
+    }
+
+    /// Append every tool call to an audit log at `path`. Overrides `audit_log`
+    /// in the config file.
+    pub fn audit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audit_log = Some(path.into());
+        self
+    }
+
+    /// Resolve the configuration without building a client.
+    pub fn resolve(&self) -> Result<ClientConfig> {
+        let mut config = ClientConfig::discover(
+            &self.explicit,
+            self.timeout,
+            self.retry.as_ref(),
+            &self.default_retry,
+            self.config_path.as_deref(),
+        )?;
+        if let Some(token) = &self.token {
+            config.token = Some(token.clone());
+        }
+        if let Some(bytes) = self.max_response_size {
+            config.max_response_size = bytes;
+        }
+        if let Some(path) = &self.audit_log {
+            config.audit_log = Some(path.clone());
+        }
+        Ok(config)
+    }
+
+    pub fn build(self) -> Result<ThunderbirdClient> {
+        let config = self.resolve()?;
+        let client = if let Some(path) = &self.replay {
+            ThunderbirdClient::with_transport(ReplayTransport::open(path)?, config.retry.clone())
+        } else {
+            let http = HttpTransport::new(&config);
+            match &self.record {
+                Some(path) => ThunderbirdClient::with_transport(
+                    RecordingTransport::create(path, http)?,
+                    config.retry.clone(),
+                ),
+                None => ThunderbirdClient::with_transport(http, config.retry.clone()),
+            }
+        };
+        configure(client, &config)
+    }
+
+    /// Build a client that sends through `transport`. Endpoint and token
+    /// settings are ignored; the retry policy still applies.
+    pub fn build_with_transport(
+        self,
+        transport: impl Transport + 'static,
+    ) -> Result<ThunderbirdClient> {
+        let config = self.resolve()?;
+        let client = ThunderbirdClient::with_transport(transport, config.retry.clone());
+        configure(client, &config)
+    }
+
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<crate::async_client::AsyncThunderbirdClient> {
+        Ok(crate::async_client::AsyncThunderbirdClient::with_config(
+            &self.resolve()?,
+        ))
+    }
+}
+
+fn configure(client: ThunderbirdClient, config: &ClientConfig) -> Result<ThunderbirdClient> {
+    let client = client.attachment_policy(config.attachments.clone());
+    Ok(match &config.audit_log {
+        Some(path) => client.audit(AuditLog::open(path, config.audit_max_size)?),
+        None => client,
+    })
 }
 
 impl Default for ThunderbirdClient {
@@ -20,95 +217,460 @@ impl Default for ThunderbirdClient {
 }
 
 impl ThunderbirdClient {
+    /// Client for the default endpoint, without discovery.
+    /// Use [`ThunderbirdClient::builder`] to honour env vars and config files.
     pub fn new() -> Self {
-        let agent = ureq::Agent::new_with_config(
-            ureq::config::Config::builder()
-                .http_status_as_error(false)
-                .timeout_global(Some(REQUEST_TIMEOUT))
-                .build(),
-        );
+        Self::with_config(&ClientConfig::default())
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub fn with_config(config: &ClientConfig) -> Self {
+        Self::with_transport(HttpTransport::new(config), config.retry.clone())
+            .attachment_policy(config.attachments.clone())
+    }
+
+    /// Client that sends requests through `transport` instead of HTTP.
+    pub fn with_transport(transport: impl Transport + 'static, retry: RetryPolicy) -> Self {
         Self {
-            agent,
-            url: format!("http://localhost:{}/", THUNDERBIRD_PORT),
+            transport: Box::new(transport),
+            retry,
+            observer: None,
+            audit: None,
+            attachments: AttachmentPolicy::default(),
         }
     }
 
+    /// Which files `sendMail`, `replyToMessage` and `forwardMessage` may
+    /// attach. See [`crate::attachments`].
+    pub fn attachment_policy(mut self, policy: AttachmentPolicy) -> Self {
+        self.attachments = policy;
+        self
+    }
+
+    /// Append every tool call to `log`. A failed write is reported as
+    /// [`ClientEvent::AuditFailed`]; the call itself still succeeds.
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.as_ref()
+    }
+
+    /// Stop auditing and hand the log over, to a caller that records only
+    /// some of its calls, like the MCP bridge.
+    pub fn take_audit_log(&mut self) -> Option<AuditLog> {
+        self.audit.take()
+    }
+
+    /// Call `observer` for retries, repaired responses, failures and completed calls.
+    pub fn on_event(mut self, observer: impl Fn(&ClientEvent) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    fn emit(&self, event: ClientEvent) {
+        if let Some(observer) = &self.observer {
+            observer(&event);
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        self.transport.endpoint()
+    }
+
     /// Send a raw JSON-RPC request and return the parsed response.
-    /// Uses sanitize_json as fallback if the response contains invalid control chars.
-    pub fn send_raw(&self, request: &JsonRpcRequest) -> JsonRpcResponse {
-        let body = match serde_json::to_string(request) {
-            Ok(b) => b,
-            Err(e) => {
-                return JsonRpcResponse::error(
-                    request.id.clone(),
-                    -32700,
-                    format!("Failed to serialize request: {}", e),
-                );
-            }
-        };
+    /// Uses sanitize_json as fallback if the response contains invalid control chars,
+    /// and replaces invalid UTF-8 rather than failing.
+    ///
+    /// A JSON-RPC error object from the extension is returned as `Ok`, so it
+    /// can be passed through unchanged; transport and parse failures are `Err`.
+    ///
+    /// Read-only methods are retried according to the client's [`RetryPolicy`].
+    /// Attachments of compose tools are checked against the client's
+    /// [`AttachmentPolicy`] first, and a refusal is returned as a tool error.
+    pub fn send_raw(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+        self.send_raw_lossy(request).map(|(response, _)| response)
+    }
 
-        let response = match self
-            .agent
-            .post(&self.url)
-            .content_type("application/json")
-            .send(&body)
-        {
-            Ok(r) => r,
-            Err(e) => {
-                return JsonRpcResponse::error(
-                    request.id.clone(),
-                    -32603,
-                    format!(
-                        "Connection failed: {}. Is Thunderbird running with the API extension?",
-                        e
-                    ),
-                );
-            }
+    /// [`send_raw`](Self::send_raw), also telling whether invalid UTF-8 in
+    /// the response was replaced with U+FFFD.
+    pub(crate) fn send_raw_lossy(
+        &self,
+        request: &JsonRpcRequest,
+    ) -> Result<(JsonRpcResponse, bool)> {
+        let method = request.method.as_str();
+        let start = Instant::now();
+        let result = match self.attachments.check(method, request.params.as_ref()) {
+            // Answered the way the tool answers a failure, without sending anything
+            Err(reason) => Ok(Parsed::as_sent(JsonRpcResponse::success(
+                request.id.clone(),
+                json!({ "error": reason }),
+            ))),
+            Ok(None) => self.transmit(request),
+            Ok(Some(params)) => self.transmit(&JsonRpcRequest {
+                params: Some(params),
+                ..request.clone()
+            }),
         };
+        let (result, lossy) = match result {
+            Ok(parsed) => (Ok(parsed.response), parsed.lossy),
+            Err(error) => (Err(error), false),
+        };
+        self.write_audit(request, &result, start.elapsed());
+        match result {
+            Ok(response) => {
+                let elapsed = start.elapsed();
+                self.emit(ClientEvent::Completed { method, elapsed });
+                Ok((response, lossy))
+            }
+            Err(error) => {
+                self.emit(ClientEvent::Failed {
+                    method,
+                    error: &error,
+                });
+                Err(error)
+            }
+        }
+    }
 
-        let data = match response.into_body().read_to_string() {
-            Ok(d) => d,
-            Err(e) => {
-                return JsonRpcResponse::error(
-                    request.id.clone(),
-                    -32603,
-                    format!("Failed to read response body: {}", e),
-                );
+    /// Send `request`, retrying read-only methods, and parse the response.
+    fn transmit(&self, request: &JsonRpcRequest) -> Result<Parsed> {
+        let body = encode_request(request)?;
+        let method = request.method.as_str();
+        let mut backoff = self.retry.backoff();
+        let result = loop {
+            match self.transport.send(&body) {
+                Err(e) if self.retry.should_retry(method, &e) => match backoff.next_delay() {
+                    Some(delay) => {
+                        self.emit(ClientEvent::Retrying {
+                            method,
+                            delay,
+                            error: &e,
+                        });
+                        std::thread::sleep(delay);
+                    }
+                    None => break Err(e),
+                },
+                result => break result.and_then(|data| parse_response(&data)),
             }
         };
 
-        // Try parsing as-is, then with sanitization as fallback
-        match serde_json::from_str::<JsonRpcResponse>(&data) {
-            Ok(resp) => resp,
-            Err(_) => {
-                let sanitized = sanitize_json(&data);
-                match serde_json::from_str::<JsonRpcResponse>(&sanitized) {
-                    Ok(resp) => resp,
-                    Err(e) => JsonRpcResponse::error(
-                        request.id.clone(),
-                        -32700,
-                        format!("Invalid JSON from Thunderbird: {}", e),
-                    ),
-                }
+        result.inspect(|parsed| {
+            if parsed.sanitized {
+                let lossy = parsed.lossy;
+                self.emit(ClientEvent::Sanitized { method, lossy });
             }
+        })
+    }
+
+    fn write_audit(
+        &self,
+        request: &JsonRpcRequest,
+        result: &Result<JsonRpcResponse>,
+        elapsed: Duration,
+    ) {
+        let Some(log) = &self.audit else { return };
+        let Some(entry) = AuditEntry::new(request, result, elapsed) else {
+            return;
+        };
+        if let Err(error) = log.write(entry) {
+            self.emit(ClientEvent::AuditFailed {
+                method: &request.method,
+                error: &error,
+            });
         }
     }
 
     /// Call a tool on the Thunderbird extension and return the result directly.
+    /// JSON-RPC errors and `{ "error": ... }` results are both returned as errors.
     pub fn call_tool(&self, name: &str, args: Value) -> Result<Value> {
-        let request = JsonRpcRequest {
-            jsonrpc: "2.0".into(),
-            id: Some(Value::Number(1.into())),
-            method: name.into(),
-            params: Some(args),
-        };
+        tool_result(self.send_raw(&tool_request(name, args))?)
+    }
+
+    /// Call a tool with typed arguments and deserialize its result.
+    pub fn call<A: Serialize, R: DeserializeOwned>(&self, name: &str, args: &A) -> Result<R> {
+        let result = self.call_tool(name, encode_args(name, args)?)?;
+        decode_result(name, result)
+    }
+
+    pub fn list_accounts(&self) -> Result<Vec<Account>> {
+        self.call("listAccounts", &serde_json::json!({}))
+    }
+
+    pub fn search_messages(&self, args: &SearchArgs) -> Result<Vec<MessageSummary>> {
+        self.call("searchMessages", args)
+    }
+
+    pub fn get_message(&self, args: &GetMessageArgs) -> Result<Message> {
+        self.call("getMessage", args)
+    }
+
+    pub fn list_folders(&self, args: &ListFoldersArgs) -> Result<Vec<Folder>> {
+        self.call("listFolders", args)
+    }
+
+    pub fn update_message(&self, args: &UpdateMessageArgs) -> Result<UpdateResult> {
+        self.call("updateMessage", args)
+    }
+
+    pub fn send_mail(&self, args: &SendMailArgs) -> Result<ComposeResult> {
+        self.call("sendMail", args)
+    }
+
+    pub fn reply_to_message(&self, args: &ReplyArgs) -> Result<ComposeResult> {
+        self.call("replyToMessage", args)
+    }
+
+    pub fn forward_message(&self, args: &ForwardArgs) -> Result<ComposeResult> {
+        self.call("forwardMessage", args)
+    }
+
+    pub fn search_contacts(&self, args: &SearchContactsArgs) -> Result<Vec<Contact>> {
+        self.call("searchContacts", args)
+    }
+
+    pub fn list_calendars(&self) -> Result<Vec<Calendar>> {
+        self.call("listCalendars", &serde_json::json!({}))
+    }
+
+    pub fn create_event(&self, args: &CreateEventArgs) -> Result<ComposeResult> {
+        self.call("createEvent", args)
+    }
+
+    /// Progress of the call sent with `_meta.progressToken` set to `token`.
+    /// `None` if the extension does not track that call, or has finished it.
+    pub fn get_progress(&self, token: &str) -> Result<Option<Progress>> {
+        self.call(
+            "getProgress",
+            &serde_json::json!({ "progressToken": token }),
+        )
+    }
+}
+
+// Request encoding and response decoding shared with the async client.
+
+pub(crate) fn encode_request(request: &JsonRpcRequest) -> Result<String> {
+    serde_json::to_string(request).map_err(|e| ThunderbirdError::Protocol {
+        code: INTERNAL_ERROR,
+        message: format!("Failed to serialize request: {}", e),
+    })
+}
+
+/// A parsed response, and what it took to parse it.
+pub(crate) struct Parsed {
+    pub(crate) response: JsonRpcResponse,
+    /// The body was not valid JSON as sent and went through [`sanitize_json`].
+    pub(crate) sanitized: bool,
+    /// Invalid UTF-8 in the body was replaced with U+FFFD.
+    pub(crate) lossy: bool,
+}
+
+impl Parsed {
+    fn as_sent(response: JsonRpcResponse) -> Self {
+        Self {
+            response,
+            sanitized: false,
+            lossy: false,
+        }
+    }
+}
+
+/// Parse a response body. If it is not valid UTF-8 or contains raw control
+/// characters, decode it lossily, run it through [`sanitize_json`] and try
+/// again, noting both in the returned [`Parsed`].
+pub(crate) fn parse_response(data: &[u8]) -> Result<Parsed> {
+    // Try parsing as-is, without copying, then with sanitization as fallback
+    match serde_json::from_slice::<JsonRpcResponse>(data) {
+        Ok(response) => Ok(Parsed::as_sent(response)),
+        Err(_) => {
+            let (text, lossy) = decode_lossy(data);
+            let sanitized = sanitize_json(&text);
+            let response = serde_json::from_str::<JsonRpcResponse>(&sanitized).map_err(|e| {
+                ThunderbirdError::InvalidResponse(format!("Invalid JSON from Thunderbird: {}", e))
+            })?;
+            Ok(Parsed {
+                response,
+                sanitized: true,
+                lossy,
+            })
+        }
+    }
+}
+
+pub(crate) fn tool_request(name: &str, args: Value) -> JsonRpcRequest {
+    JsonRpcRequest {
+        jsonrpc: "2.0".into(),
+        id: Some(Value::Number(1.into())),
+        method: name.into(),
+        params: Some(args),
+    }
+}
+
+/// Unwrap a tool response: JSON-RPC errors and `{ "error": ... }` results
+/// both become `Err`.
+pub(crate) fn tool_result(response: JsonRpcResponse) -> Result<Value> {
+    if let Some(err) = response.error {
+        return Err(ThunderbirdError::Protocol {
+            code: err.code,
+            message: err.message,
+        });
+    }
+
+    let result = response
+        .result
+        .ok_or_else(|| ThunderbirdError::InvalidResponse("No result in response".into()))?;
+    check_tool_result(&result)?;
+    Ok(result)
+}
+
+pub(crate) fn encode_args<A: Serialize>(name: &str, args: &A) -> Result<Value> {
+    serde_json::to_value(args).map_err(|e| {
+        ThunderbirdError::InvalidArgument(format!("Failed to serialize {} arguments: {}", name, e))
+    })
+}
 
-        let response = self.send_raw(&request);
+pub(crate) fn decode_result<R: DeserializeOwned>(name: &str, result: Value) -> Result<R> {
+    serde_json::from_value(result).map_err(|e| {
+        ThunderbirdError::InvalidResponse(format!("Unexpected {} result: {}", name, e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ToolErrorCode;
+    use crate::transport::MemoryTransport;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
-        if let Some(err) = response.error {
-            anyhow::bail!("{}", err.message);
+    fn not_running() -> ThunderbirdError {
+        ThunderbirdError::NotRunning {
+            endpoint: "memory".into(),
+            message: "Connection refused".into(),
         }
+    }
 
-        response.result.context("No result in response")
+    /// Fails with `NotRunning` for the first `failures` calls, then answers `[]`.
+    fn flaky(failures: usize) -> (MemoryTransport, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let transport = MemoryTransport::raw(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                Err(not_running())
+            } else {
+                Ok(r#"{"jsonrpc":"2.0","id":1,"result":[]}"#.into())
+            }
+        });
+        (transport, calls)
+    }
+
+    #[test]
+    fn raw_control_chars_are_sanitized() {
+        let client = ThunderbirdClient::with_transport(
+            MemoryTransport::raw(|_| {
+                Ok("{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":\"a\nb\x01\"}".into())
+            }),
+            RetryPolicy::none(),
+        );
+        assert_eq!(
+            client.call_tool("listAccounts", json!({})).unwrap(),
+            json!("a\nb")
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_flagged() {
+        let client = ThunderbirdClient::with_transport(
+            MemoryTransport::raw(|_| {
+                Ok(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":\"caf\xe9\"}".to_vec())
+            }),
+            RetryPolicy::none(),
+        );
+        let (response, lossy) = client
+            .send_raw_lossy(&tool_request("getMessage", json!({})))
+            .unwrap();
+        assert_eq!(response.result, Some(json!("caf\u{FFFD}")));
+        assert!(lossy);
+    }
+
+    #[test]
+    fn error_envelope_is_tool_error() {
+        let client = ThunderbirdClient::with_transport(
+            MemoryTransport::new(|req| {
+                JsonRpcResponse::success(req.id, json!({ "error": "Message not found: x" }))
+            }),
+            RetryPolicy::none(),
+        );
+        let err = client
+            .get_message(&GetMessageArgs {
+                message_id: "x".into(),
+                folder_path: "mailbox://x/Inbox".into(),
+                save_attachments: false,
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ThunderbirdError::Tool {
+                code: ToolErrorCode::NotFound,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn read_only_tools_retry_until_reachable() {
+        let (transport, calls) = flaky(2);
+        let client = ThunderbirdClient::with_transport(
+            transport,
+            RetryPolicy::with_max_elapsed(Duration::from_secs(10)),
+        );
+        assert!(client.list_calendars().unwrap().is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn events_are_reported() {
+        let (transport, _) = flaky(1);
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let client = ThunderbirdClient::with_transport(
+            transport,
+            RetryPolicy::with_max_elapsed(Duration::from_secs(10)),
+        )
+        .on_event(move |event| {
+            let name = match event {
+                ClientEvent::Retrying { method, .. } => format!("retrying {}", method),
+                ClientEvent::Sanitized { method, .. } => format!("sanitized {}", method),
+                ClientEvent::Failed { method, .. } => format!("failed {}", method),
+                ClientEvent::Completed { method, .. } => format!("completed {}", method),
+                ClientEvent::AuditFailed { method, .. } => format!("not audited {}", method),
+            };
+            sink.lock().unwrap().push(name);
+        });
+        client.list_calendars().unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["retrying listCalendars", "completed listCalendars"]
+        );
+    }
+
+    #[test]
+    fn updates_are_not_retried() {
+        let (transport, calls) = flaky(1);
+        let client = ThunderbirdClient::with_transport(
+            transport,
+            RetryPolicy::with_max_elapsed(Duration::from_secs(10)),
+        );
+        let err = client
+            .call_tool("updateMessage", json!({ "trash": true }))
+            .unwrap_err();
+        assert!(matches!(err, ThunderbirdError::NotRunning { .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
     }
 }
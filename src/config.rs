@@ -0,0 +1,402 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::attachments::{self, AttachmentPolicy};
+use crate::audit;
+use crate::error::{Result, ThunderbirdError};
+use crate::retry::RetryPolicy;
+
+pub const DEFAULT_PORT: u16 = 8756;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Large enough for messages with big inline HTML; attachments are not inlined.
+pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Full endpoint URL, e.g. `http://localhost:8756/`.
+pub const ENDPOINT_ENV: &str = "THUNDERBIRD_API_ENDPOINT";
+/// Port on localhost. Ignored if `THUNDERBIRD_API_ENDPOINT` is set.
+pub const PORT_ENV: &str = "THUNDERBIRD_API_PORT";
+/// Overrides the config file location.
+pub const CONFIG_ENV: &str = "THUNDERBIRD_API_CONFIG";
+/// Bearer token for the extension. Overrides the token file.
+pub const TOKEN_ENV: &str = "THUNDERBIRD_API_TOKEN";
+/// Bridge log level: `debug` ... `emergency`, or `off`.
+pub const LOG_ENV: &str = "THUNDERBIRD_API_LOG";
+
+const APP_DIR: &str = "thunderbird-api";
+
+/// Settings a `ThunderbirdClient` is built from, after discovery.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub endpoint: String,
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+    /// Sent as `Authorization: Bearer <token>`.
+    pub token: Option<String>,
+    /// Larger response bodies are rejected, in bytes.
+    pub max_response_size: u64,
+    /// Where tool calls are logged, if anywhere. See [`crate::audit`].
+    pub audit_log: Option<PathBuf>,
+    /// The audit log is rotated at this size, in bytes.
+    pub audit_max_size: u64,
+    /// Which files compose tools may attach.
+    pub attachments: AttachmentPolicy,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: localhost_url(DEFAULT_PORT),
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::none(),
+            token: None,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            audit_log: None,
+            audit_max_size: audit::DEFAULT_MAX_SIZE,
+            attachments: AttachmentPolicy::default(),
+        }
+    }
+}
+
+/// One layer of endpoint settings. Within a layer, `endpoint` wins over `port`.
+#[derive(Debug, Default, Clone)]
+pub struct EndpointSource {
+    pub endpoint: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl EndpointSource {
+    fn url(&self) -> Option<String> {
+        if let Some(endpoint) = &self.endpoint {
+            return Some(normalize_endpoint(endpoint));
+        }
+        self.port.map(localhost_url)
+    }
+
+    fn from_env() -> Result<Self> {
+        let endpoint = env::var(ENDPOINT_ENV).ok().filter(|s| !s.trim().is_empty());
+        let port =
+            match env::var(PORT_ENV) {
+                Ok(p) if !p.trim().is_empty() => Some(p.trim().parse().map_err(|_| {
+                    ThunderbirdError::Config(format!("Invalid {}: {}", PORT_ENV, p))
+                })?),
+                _ => None,
+            };
+        Ok(Self { endpoint, port })
+    }
+}
+
+/// Contents of `config.toml`. Every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub endpoint: Option<String>,
+    pub port: Option<u16>,
+    pub timeout_secs: Option<u64>,
+    /// How long to keep retrying read-only tools while Thunderbird is unreachable.
+    /// 0 disables retries.
+    pub retry_secs: Option<u64>,
+    /// Largest response accepted from the extension, in megabytes.
+    pub max_response_mb: Option<u64>,
+    /// Append every tool call to this JSONL file.
+    pub audit_log: Option<PathBuf>,
+    /// Rotate the audit log at this size, in megabytes.
+    pub audit_max_mb: Option<u64>,
+    /// Tools the MCP bridge exposes: `readonly`, `compose`, `full` or `custom`.
+    pub tool_profile: Option<String>,
+    /// Tools the bridge exposes on top of the profile.
+    pub allow_tools: Vec<String>,
+    /// Tools the bridge never exposes.
+    pub deny_tools: Vec<String>,
+    /// Ask the user before `updateMessage` trashes or moves mail.
+    pub confirm_destructive: Option<bool>,
+    /// Ask the user before compose tools attach files.
+    pub confirm_attachments: Option<bool>,
+    /// `allow` or `deny` calls that need confirming when the client cannot ask.
+    pub confirm_fallback: Option<String>,
+    /// Compose tools may only attach files inside these directories.
+    pub attachment_dirs: Vec<PathBuf>,
+    /// Patterns of files compose tools never attach. Replaces the built-in list.
+    pub attachment_deny: Option<Vec<String>>,
+    /// Largest file compose tools may attach, in megabytes.
+    pub attachment_max_mb: Option<u64>,
+    /// Per-tool overrides of the bridge's tool annotations.
+    pub tool_annotations: HashMap<String, HintOverride>,
+}
+
+/// One tool's `[tool_annotations.<tool>]` table. Hints left out keep the
+/// bridge's value; see [`crate::mcp::annotations`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HintOverride {
+    pub read_only_hint: Option<bool>,
+    pub destructive_hint: Option<bool>,
+    pub idempotent_hint: Option<bool>,
+    pub open_world_hint: Option<bool>,
+}
+
+impl ConfigFile {
+    /// Load from `path`. A missing file is treated as empty.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = match fs::read_to_string(path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(ThunderbirdError::Config(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        toml::from_str(&data).map_err(|e| {
+            ThunderbirdError::Config(format!("Invalid config file {}: {}", path.display(), e))
+        })
+    }
+
+    fn endpoint_source(&self) -> EndpointSource {
+        EndpointSource {
+            endpoint: self.endpoint.clone(),
+            port: self.port,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Resolve the endpoint from, in order: `explicit`, the environment,
+    /// the config file, the extension's port file, and the default port.
+    /// The token comes from `THUNDERBIRD_API_TOKEN`, else the token file.
+    ///
+    /// `timeout` and `retry` win over the config file; `default_retry` is used
+    /// when neither sets a retry policy.
+    pub fn discover(
+        explicit: &EndpointSource,
+        timeout: Option<Duration>,
+        retry: Option<&RetryPolicy>,
+        default_retry: &RetryPolicy,
+        config_path: Option<&Path>,
+    ) -> Result<Self> {
+        let env_source = EndpointSource::from_env()?;
+        let file = match config_path.map(Path::to_path_buf).or_else(config_file_path) {
+            Some(path) => ConfigFile::load(&path)?,
+            None => ConfigFile::default(),
+        };
+        let port_file = EndpointSource {
+            endpoint: None,
+            port: read_port_file(&port_file_path()),
+        };
+
+        let endpoint = resolve_endpoint(&[
+            explicit.clone(),
+            env_source,
+            file.endpoint_source(),
+            port_file,
+        ]);
+        let timeout = timeout
+            .or(file.timeout_secs.map(Duration::from_secs))
+            .unwrap_or(DEFAULT_TIMEOUT);
+        let retry = retry
+            .cloned()
+            .or(file
+                .retry_secs
+                .map(|s| RetryPolicy::with_max_elapsed(Duration::from_secs(s))))
+            .unwrap_or_else(|| default_retry.clone());
+        let token = match env::var(TOKEN_ENV) {
+            Ok(t) if !t.trim().is_empty() => Some(t.trim().to_string()),
+            _ => token_file_path()
+                .map(|p| read_token_file(&p))
+                .transpose()?
+                .flatten(),
+        };
+
+        let max_response_size = file
+            .max_response_mb
+            .map(|mb| mb.saturating_mul(1024 * 1024))
+            .unwrap_or(DEFAULT_MAX_RESPONSE_SIZE);
+
+        let audit_max_size = file
+            .audit_max_mb
+            .map(|mb| mb.saturating_mul(1024 * 1024))
+            .unwrap_or(audit::DEFAULT_MAX_SIZE);
+
+        let attachments = AttachmentPolicy {
+            dirs: file.attachment_dirs,
+            deny: file
+                .attachment_deny
+                .unwrap_or_else(|| AttachmentPolicy::default().deny),
+            max_size: file
+                .attachment_max_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024))
+                .unwrap_or(attachments::DEFAULT_MAX_SIZE),
+        };
+
+        Ok(Self {
+            endpoint,
+            timeout,
+            retry,
+            token,
+            max_response_size,
+            audit_log: file.audit_log,
+            audit_max_size,
+            attachments,
+        })
+    }
+}
+
+/// First source that specifies anything wins; falls back to the default port.
+pub fn resolve_endpoint(sources: &[EndpointSource]) -> String {
+    sources
+        .iter()
+        .find_map(EndpointSource::url)
+        .unwrap_or_else(|| localhost_url(DEFAULT_PORT))
+}
+
+/// `$XDG_CONFIG_HOME/thunderbird-api`, or `~/.config/thunderbird-api`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join(APP_DIR))
+}
+
+/// `$THUNDERBIRD_API_CONFIG`, or `config.toml` in [`config_dir`].
+pub fn config_file_path() -> Option<PathBuf> {
+    env::var_os(CONFIG_ENV)
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config_dir().map(|d| d.join("config.toml")))
+}
+
+/// User-defined MCP prompts, one `*.toml` file each.
+pub fn prompts_dir() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("prompts"))
+}
+
+/// Bearer token the extension generates on first run, readable only by the user.
+/// Must match `loadOrCreateToken` in extension/mcp_server/api.js.
+pub fn token_file_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("token"))
+}
+
+/// A missing or empty file means no token; the extension will then reject requests.
+fn read_token_file(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(data) => Ok(Some(data.trim().to_string()).filter(|t| !t.is_empty())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ThunderbirdError::Config(format!(
+            "Failed to read token file {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Where the extension writes its listening port at startup.
+/// Must match `writePortFile` in extension/mcp_server/api.js.
+///
+/// Every Thunderbird profile writes the same file, so with several profiles
+/// running it names the one started last. Set the port of the others
+/// explicitly.
+pub fn port_file_path() -> PathBuf {
+    let base = env::var_os("XDG_RUNTIME_DIR")
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    base.join(APP_DIR).join("port")
+}
+
+/// The endpoint the port file names, if it names one.
+pub fn port_file_endpoint() -> Option<String> {
+    read_port_file(&port_file_path()).map(localhost_url)
+}
+
+fn read_port_file(path: &Path) -> Option<u16> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn localhost_url(port: u16) -> String {
+    format!("http://localhost:{}/", port)
+}
+
+/// Accept `host:port` as shorthand for `http://host:port/`.
+fn normalize_endpoint(endpoint: &str) -> String {
+    let endpoint = endpoint.trim();
+    if endpoint.contains("://") {
+        endpoint.to_string()
+    } else {
+        format!("http://{}/", endpoint.trim_end_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(p: u16) -> EndpointSource {
+        EndpointSource {
+            endpoint: None,
+            port: Some(p),
+        }
+    }
+
+    fn endpoint(e: &str) -> EndpointSource {
+        EndpointSource {
+            endpoint: Some(e.into()),
+            port: None,
+        }
+    }
+
+    #[test]
+    fn default_when_nothing_set() {
+        let sources = [EndpointSource::default(), EndpointSource::default()];
+        assert_eq!(resolve_endpoint(&sources), "http://localhost:8756/");
+    }
+
+    #[test]
+    fn earlier_source_wins() {
+        let sources = [port(9001), endpoint("http://127.0.0.1:9002/"), port(9003)];
+        assert_eq!(resolve_endpoint(&sources), "http://localhost:9001/");
+    }
+
+    #[test]
+    fn unset_sources_fall_through() {
+        let sources = [
+            EndpointSource::default(),
+            EndpointSource::default(),
+            port(9003),
+        ];
+        assert_eq!(resolve_endpoint(&sources), "http://localhost:9003/");
+    }
+
+    #[test]
+    fn endpoint_beats_port_in_same_source() {
+        let source = EndpointSource {
+            endpoint: Some("http://127.0.0.1:9000/".into()),
+            port: Some(9001),
+        };
+        assert_eq!(resolve_endpoint(&[source]), "http://127.0.0.1:9000/");
+    }
+
+    #[test]
+    fn endpoint_without_scheme() {
+        assert_eq!(
+            normalize_endpoint("127.0.0.1:9000"),
+            "http://127.0.0.1:9000/"
+        );
+    }
+
+    #[test]
+    fn parses_config_file() {
+        let file: ConfigFile =
+            toml::from_str("port = 9100\ntimeout_secs = 5\nretry_secs = 0\n").unwrap();
+        assert_eq!(file.port, Some(9100));
+        assert_eq!(file.timeout_secs, Some(5));
+        assert_eq!(file.retry_secs, Some(0));
+        assert!(file.endpoint.is_none());
+        assert!(file.deny_tools.is_empty());
+    }
+}
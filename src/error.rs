@@ -0,0 +1,214 @@
+use serde_json::Value;
+use std::fmt;
+
+use crate::types::JsonRpcResponse;
+
+pub type Result<T, E = ThunderbirdError> = std::result::Result<T, E>;
+
+/// JSON-RPC code used for failures that happen before the extension answers.
+pub const INTERNAL_ERROR: i32 = -32603;
+pub const PARSE_ERROR: i32 = -32700;
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+
+#[derive(Debug)]
+pub enum ThunderbirdError {
+    /// Nothing is listening: Thunderbird is closed or the extension is disabled.
+    NotRunning { endpoint: String, message: String },
+    /// The extension accepted the connection but did not answer in time.
+    Timeout { endpoint: String },
+    /// HTTP failure, or a JSON-RPC error object returned by the extension.
+    Protocol { code: i32, message: String },
+    /// The extension rejected the bearer token, or none was sent.
+    Auth(String),
+    /// The tool ran and reported a failure, e.g. `{ "error": "Message not found: ..." }`.
+    Tool {
+        code: ToolErrorCode,
+        message: String,
+    },
+    /// The response was not JSON-RPC or did not have the expected shape.
+    InvalidResponse(String),
+    /// Arguments could not be encoded for the extension.
+    InvalidArgument(String),
+    /// Invalid config file or environment variable.
+    Config(String),
+}
+
+/// Coarse classification of tool-level errors. The extension only sends a
+/// message, so this is derived from its wording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolErrorCode {
+    /// Message, folder, calendar or Trash folder does not exist.
+    NotFound,
+    /// A required Thunderbird component (calendar, main window) is unavailable.
+    Unavailable,
+    /// An argument was rejected, e.g. an unparseable date or a read-only calendar.
+    InvalidArgument,
+    Failed,
+}
+
+impl ToolErrorCode {
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_ascii_lowercase();
+        if lower.contains("not found") {
+            ToolErrorCode::NotFound
+        } else if lower.contains("not available") || lower.contains("no thunderbird window") {
+            ToolErrorCode::Unavailable
+        } else if lower.starts_with("invalid") || lower.contains("read-only") {
+            ToolErrorCode::InvalidArgument
+        } else {
+            ToolErrorCode::Failed
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolErrorCode::NotFound => "not_found",
+            ToolErrorCode::Unavailable => "unavailable",
+            ToolErrorCode::InvalidArgument => "invalid_argument",
+            ToolErrorCode::Failed => "failed",
+        }
+    }
+}
+
+impl ThunderbirdError {
+    pub fn tool(message: impl Into<String>) -> Self {
+        let message = message.into();
+        ThunderbirdError::Tool {
+            code: ToolErrorCode::classify(&message),
+            message,
+        }
+    }
+
+    /// Process exit code used by `thunderbird-cli`. Documented in the README;
+    /// keep the values stable.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ThunderbirdError::NotRunning { .. } => 3,
+            ThunderbirdError::Timeout { .. } => 4,
+            ThunderbirdError::Protocol { .. } | ThunderbirdError::InvalidResponse(_) => 5,
+            ThunderbirdError::Tool {
+                code: ToolErrorCode::NotFound,
+                ..
+            } => 7,
+            ThunderbirdError::Tool { .. } => 6,
+            // Not 2, which clap uses for usage errors
+            ThunderbirdError::InvalidArgument(_) => 10,
+            ThunderbirdError::Config(_) => 8,
+            ThunderbirdError::Auth(_) => 9,
+        }
+    }
+
+    /// JSON-RPC error code when reporting this error to a JSON-RPC peer.
+    pub fn rpc_code(&self) -> i32 {
+        match self {
+            ThunderbirdError::Protocol { code, .. } => *code,
+            ThunderbirdError::InvalidResponse(_) => PARSE_ERROR,
+            _ => INTERNAL_ERROR,
+        }
+    }
+
+    pub fn to_response(&self, id: Option<Value>) -> JsonRpcResponse {
+        JsonRpcResponse::error(id, self.rpc_code(), self.to_string())
+    }
+}
+
+impl fmt::Display for ThunderbirdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThunderbirdError::NotRunning { endpoint, message } => write!(
+                f,
+                "Connection to {} failed: {}. Is Thunderbird running with the API extension?",
+                endpoint, message
+            ),
+            ThunderbirdError::Timeout { endpoint } => {
+                write!(f, "Request to {} timed out", endpoint)
+            }
+            ThunderbirdError::Protocol { message, .. } => f.write_str(message),
+            ThunderbirdError::Auth(message) => write!(
+                f,
+                "Thunderbird rejected the request: {}. Set {} or check the extension's token file",
+                message,
+                crate::config::TOKEN_ENV
+            ),
+            ThunderbirdError::Tool { message, .. } => f.write_str(message),
+            ThunderbirdError::InvalidResponse(message) => {
+                write!(f, "Invalid response from Thunderbird: {}", message)
+            }
+            ThunderbirdError::InvalidArgument(message) => f.write_str(message),
+            ThunderbirdError::Config(message) => write!(f, "Configuration error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ThunderbirdError {}
+
+/// The single check for the extension's result envelope: a successful
+/// JSON-RPC result of the form `{ "error": "..." }` is a tool failure.
+pub fn check_tool_result(result: &Value) -> Result<()> {
+    match result.get("error").and_then(|v| v.as_str()) {
+        Some(err) => Err(ThunderbirdError::tool(err)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classify_tool_errors() {
+        assert_eq!(
+            ToolErrorCode::classify("Message not found: <abc@example.com>"),
+            ToolErrorCode::NotFound
+        );
+        assert_eq!(
+            ToolErrorCode::classify("Calendar not available"),
+            ToolErrorCode::Unavailable
+        );
+        assert_eq!(
+            ToolErrorCode::classify("Invalid startDate: tomorrow"),
+            ToolErrorCode::InvalidArgument
+        );
+        assert_eq!(
+            ToolErrorCode::classify("Move failed with status: 2147500037"),
+            ToolErrorCode::Failed
+        );
+    }
+
+    #[test]
+    fn envelope_error_is_tool_error() {
+        let err = check_tool_result(&json!({ "error": "Folder not found: x" })).unwrap_err();
+        assert_eq!(err.exit_code(), 7);
+        assert_eq!(err.to_string(), "Folder not found: x");
+    }
+
+    #[test]
+    fn envelope_without_error_passes() {
+        assert!(check_tool_result(&json!([{ "error": "nested is fine" }])).is_ok());
+        assert!(check_tool_result(&json!({ "success": true })).is_ok());
+    }
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        let not_running = ThunderbirdError::NotRunning {
+            endpoint: "http://localhost:8756/".into(),
+            message: "refused".into(),
+        };
+        let timeout = ThunderbirdError::Timeout {
+            endpoint: "http://localhost:8756/".into(),
+        };
+        assert_eq!(not_running.exit_code(), 3);
+        assert_eq!(timeout.exit_code(), 4);
+        assert_eq!(
+            ThunderbirdError::tool("Calendar not available").exit_code(),
+            6
+        );
+        assert_eq!(
+            ThunderbirdError::InvalidArgument("x".into()).exit_code(),
+            10
+        );
+    }
+}
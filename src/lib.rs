@@ -1,4 +1,17 @@
+#[cfg(feature = "async")]
+pub mod async_client;
+pub mod attachments;
+pub mod audit;
+pub mod cassette;
 pub mod cli;
 pub mod client;
+pub mod config;
+pub mod error;
+pub mod mcp;
+pub mod model;
+pub mod retry;
 pub mod sanitize;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod transport;
 pub mod types;
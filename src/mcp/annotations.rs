@@ -0,0 +1,201 @@
+//! MCP tool `annotations` for the extension's tools, which have none of their
+//! own: whether a tool only reads, can destroy mail, is safe to repeat, and
+//! reaches beyond Thunderbird.
+//!
+//! Known tools get hints from a built-in table. Tools the bridge does not
+//! know get MCP's worst case: not read-only, destructive, not idempotent and
+//! open-world. Hints the extension does send win over both, and the config
+//! file wins over everything:
+//!
+//! ```toml
+//! [tool_annotations.updateMessage]
+//! destructive_hint = false
+//! ```
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+pub use crate::config::HintOverride;
+use crate::retry::is_idempotent;
+
+/// First MCP version with tool annotations.
+pub const ANNOTATIONS_VERSION: &str = "2025-03-26";
+
+/// Hints for one tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolHints {
+    pub read_only: bool,
+    pub destructive: bool,
+    pub idempotent: bool,
+    pub open_world: bool,
+}
+
+impl ToolHints {
+    /// MCP's defaults, for tools the bridge knows nothing about.
+    pub const UNKNOWN: ToolHints = ToolHints {
+        read_only: false,
+        destructive: true,
+        idempotent: false,
+        open_world: true,
+    };
+
+    const READ: ToolHints = ToolHints {
+        read_only: true,
+        destructive: false,
+        idempotent: true,
+        open_world: false,
+    };
+
+    /// Opens a window for the user to review; each call opens another.
+    const COMPOSE: ToolHints = ToolHints {
+        read_only: false,
+        destructive: false,
+        idempotent: false,
+        open_world: true,
+    };
+
+    /// Hints from the built-in table.
+    pub fn builtin(tool: &str) -> Option<ToolHints> {
+        match tool {
+            // The tools that are retried; getMessage with saveAttachments only
+            // writes temporary files
+            _ if is_idempotent(tool) => Some(Self::READ),
+            // Nothing leaves the machine until the user presses Send
+            "sendMail" | "replyToMessage" | "forwardMessage" => Some(Self::COMPOSE),
+            "createEvent" => Some(ToolHints {
+                open_world: false,
+                ..Self::COMPOSE
+            }),
+            // Moving to Trash or another folder. Not idempotent: a repeated
+            // move fails, as the message has left the source folder
+            "updateMessage" => Some(ToolHints {
+                read_only: false,
+                destructive: true,
+                idempotent: false,
+                open_world: false,
+            }),
+            _ => None,
+        }
+    }
+
+    fn to_json(self) -> Map<String, Value> {
+        let value = json!({
+            "readOnlyHint": self.read_only,
+            "destructiveHint": self.destructive,
+            "idempotentHint": self.idempotent,
+            "openWorldHint": self.open_world,
+        });
+        value.as_object().cloned().unwrap_or_default()
+    }
+}
+
+impl HintOverride {
+    fn to_json(self) -> Map<String, Value> {
+        [
+            ("readOnlyHint", self.read_only_hint),
+            ("destructiveHint", self.destructive_hint),
+            ("idempotentHint", self.idempotent_hint),
+            ("openWorldHint", self.open_world_hint),
+        ]
+        .into_iter()
+        .filter_map(|(key, hint)| Some((key.to_string(), Value::Bool(hint?))))
+        .collect()
+    }
+}
+
+/// The built-in table plus the config file's overrides, by tool name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolAnnotations {
+    overrides: HashMap<String, HintOverride>,
+}
+
+impl ToolAnnotations {
+    pub fn new(overrides: HashMap<String, HintOverride>) -> Self {
+        Self { overrides }
+    }
+
+    /// Add `annotations` to every tool in a `listTools` result.
+    pub fn annotate(&self, list: &mut Value) {
+        let Some(tools) = list.get_mut("tools").and_then(Value::as_array_mut) else {
+            return;
+        };
+        for tool in tools {
+            let Some(name) = tool.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let mut annotations = ToolHints::builtin(name)
+                .unwrap_or(ToolHints::UNKNOWN)
+                .to_json();
+            if let Some(own) = tool.get("annotations").and_then(Value::as_object) {
+                annotations.extend(own.clone());
+            }
+            if let Some(configured) = self.overrides.get(name) {
+                annotations.extend(configured.to_json());
+            }
+            tool["annotations"] = Value::Object(annotations);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotated(annotations: &ToolAnnotations, tools: Value) -> Vec<Value> {
+        let mut list = json!({ "tools": tools });
+        annotations.annotate(&mut list);
+        list["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["annotations"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn builtin_and_unknown_tools() {
+        let tools = json!([
+            { "name": "searchMessages" },
+            { "name": "updateMessage" },
+            { "name": "deleteEverything" },
+        ]);
+        let out = annotated(&ToolAnnotations::default(), tools);
+        assert_eq!(out[0]["readOnlyHint"], true);
+        assert_eq!(out[0]["openWorldHint"], false);
+        assert_eq!(out[1]["readOnlyHint"], false);
+        assert_eq!(out[1]["destructiveHint"], true);
+        assert_eq!(out[1]["idempotentHint"], false);
+        assert_eq!(
+            out[2],
+            json!({
+                "readOnlyHint": false,
+                "destructiveHint": true,
+                "idempotentHint": false,
+                "openWorldHint": true,
+            })
+        );
+    }
+
+    #[test]
+    fn extension_hints_then_config_win() {
+        let overrides: HashMap<String, HintOverride> =
+            toml::from_str("[sendMail]\nopen_world_hint = false\n").unwrap();
+        let annotations = ToolAnnotations::new(overrides);
+        let tools = json!([{
+            "name": "sendMail",
+            "annotations": { "title": "Compose", "openWorldHint": true, "idempotentHint": true },
+        }]);
+        let out = annotated(&annotations, tools);
+        assert_eq!(out[0]["title"], "Compose");
+        assert_eq!(out[0]["idempotentHint"], true);
+        assert_eq!(out[0]["openWorldHint"], false);
+        assert_eq!(out[0]["destructiveHint"], false);
+    }
+
+    #[test]
+    fn unknown_override_keys_are_rejected() {
+        let parsed: Result<HashMap<String, HintOverride>, _> =
+            toml::from_str("[sendMail]\nreadonly = true\n");
+        assert!(parsed.is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! `completion/complete`: suggestions for prompt arguments and resource
+//! template variables, so folder URIs and identities need not be guessed.
+//!
+//! Suggestions depend only on the argument's name, so a user prompt with a
+//! `from` or `moveTo` argument is completed like the tools' own arguments:
+//!
+//! - `folderPath`, `moveTo`: folder URIs from `listFolders`
+//! - `from`: identity addresses from `listAccounts`
+//! - `calendarId`: calendar IDs from `listCalendars`
+//! - `to`, `cc`, `bcc`: contact addresses from `searchContacts`
+//!
+//! Folders, identities and calendars match the typed text anywhere in their
+//! value or name, ignoring case; those starting with it come first. Values
+//! for a resource template variable are percent-encoded, as the URI needs.
+//! Arguments whose source tool the bridge's tool profile denies get none.
+
+use serde_json::{json, Value};
+
+use super::profile::ToolPolicy;
+use super::prompts::PromptLibrary;
+use super::resources::{self, rpc_error, MESSAGE_TEMPLATE};
+use crate::client::ThunderbirdClient;
+use crate::error::INVALID_PARAMS;
+use crate::model::{ListFoldersArgs, SearchContactsArgs};
+use crate::types::JsonRpcError;
+
+/// MCP allows at most this many values in one completion.
+pub const MAX_VALUES: usize = 100;
+
+/// The argument to complete, once its reference has been checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Argument {
+    pub name: String,
+    pub value: String,
+    /// A variable of a resource template, completed with encoded values.
+    pub encoded: bool,
+}
+
+/// Check that `params.ref` names a prompt or template the bridge has, and
+/// read `params.argument`.
+pub fn argument(prompts: &PromptLibrary, params: Option<&Value>) -> Result<Argument, JsonRpcError> {
+    let params = params.ok_or_else(|| invalid("Missing params".into()))?;
+    let reference = params.get("ref");
+    let field = |name: &str| {
+        reference
+            .and_then(|r| r.get(name))
+            .and_then(Value::as_str)
+            .unwrap_or("")
+    };
+    let encoded = match field("type") {
+        "ref/prompt" if prompts.get(field("name")).is_some() => false,
+        "ref/prompt" => return Err(invalid(format!("Unknown prompt: {}", field("name")))),
+        "ref/resource" if field("uri") == MESSAGE_TEMPLATE => true,
+        "ref/resource" => {
+            return Err(invalid(format!(
+                "Unknown resource template: {}",
+                field("uri")
+            )))
+        }
+        other => return Err(invalid(format!("Unsupported reference type: {}", other))),
+    };
+
+    let argument = params.get("argument");
+    let name = argument
+        .and_then(|a| a.get("name"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("Missing parameter: argument.name".into()))?;
+    let value = argument
+        .and_then(|a| a.get("value"))
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    Ok(Argument {
+        name: name.into(),
+        value: value.into(),
+        encoded,
+    })
+}
+
+/// Answer `completion/complete` for `argument`. Arguments without a source
+/// of suggestions, or whose source `tools` denies, get an empty list.
+pub fn complete(
+    client: &ThunderbirdClient,
+    tools: &ToolPolicy,
+    argument: &Argument,
+) -> Result<Value, JsonRpcError> {
+    let source = match argument.name.as_str() {
+        "folderPath" | "moveTo" => Some("listFolders"),
+        "from" => Some("listAccounts"),
+        "calendarId" => Some("listCalendars"),
+        "to" | "cc" | "bcc" => Some("searchContacts"),
+        _ => None,
+    };
+    if source.is_some_and(|tool| !tools.allows(tool)) {
+        return Ok(completion(Vec::new()));
+    }
+    let decoded = argument
+        .encoded
+        .then(|| resources::decode(&argument.value))
+        .flatten();
+    let typed = decoded.as_deref().unwrap_or(&argument.value);
+    let values = match argument.name.as_str() {
+        "folderPath" | "moveTo" => {
+            let folders = client
+                .list_folders(&ListFoldersArgs::default())
+                .map_err(rpc_error)?;
+            matching(folders.into_iter().map(|f| (f.path, f.name)), typed)
+        }
+        "from" => {
+            let accounts = client.list_accounts().map_err(rpc_error)?;
+            let identities = accounts
+                .into_iter()
+                .flat_map(|a| a.identities)
+                .map(|i| (i.email, i.name));
+            matching(identities, typed)
+        }
+        "calendarId" => {
+            let calendars = client.list_calendars().map_err(rpc_error)?;
+            matching(calendars.into_iter().map(|c| (c.id, c.name)), typed)
+        }
+        "to" | "cc" | "bcc" => {
+            let contacts = client
+                .search_contacts(&SearchContactsArgs {
+                    query: typed.into(),
+                })
+                .map_err(rpc_error)?;
+            let mut emails: Vec<String> = Vec::new();
+            for email in contacts.into_iter().map(|c| c.email) {
+                if !email.is_empty() && !emails.contains(&email) {
+                    emails.push(email);
+                }
+            }
+            emails
+        }
+        _ => Vec::new(),
+    };
+    if argument.encoded {
+        return Ok(completion(
+            values.iter().map(|v| resources::encode(v)).collect(),
+        ));
+    }
+    Ok(completion(values))
+}
+
+/// Values whose value or name contains `typed`, prefix matches first,
+/// without duplicates.
+fn matching(candidates: impl Iterator<Item = (String, String)>, typed: &str) -> Vec<String> {
+    let typed = typed.to_lowercase();
+    let mut prefixed = Vec::new();
+    let mut contained = Vec::new();
+    for (value, name) in candidates {
+        if value.is_empty() || prefixed.contains(&value) || contained.contains(&value) {
+            continue;
+        }
+        let (lower_value, lower_name) = (value.to_lowercase(), name.to_lowercase());
+        if lower_value.starts_with(&typed) || lower_name.starts_with(&typed) {
+            prefixed.push(value);
+        } else if lower_value.contains(&typed) || lower_name.contains(&typed) {
+            contained.push(value);
+        }
+    }
+    prefixed.extend(contained);
+    prefixed
+}
+
+fn completion(mut values: Vec<String>) -> Value {
+    let total = values.len();
+    values.truncate(MAX_VALUES);
+    json!({
+        "completion": {
+            "values": values,
+            "total": total,
+            "hasMore": total > MAX_VALUES,
+        }
+    })
+}
+
+fn invalid(message: String) -> JsonRpcError {
+    JsonRpcError {
+        code: INVALID_PARAMS,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::profile::Profile;
+    use crate::retry::RetryPolicy;
+    use crate::transport::MemoryTransport;
+    use crate::types::JsonRpcResponse;
+
+    fn client() -> ThunderbirdClient {
+        ThunderbirdClient::with_transport(
+            MemoryTransport::new(|req| {
+                let result = match req.method.as_str() {
+                    "listFolders" => json!([
+                        { "name": "Inbox", "path": "imap://me@example.com/INBOX" },
+                        { "name": "Archive", "path": "imap://me@example.com/Archives" },
+                        { "name": "Old mail", "path": "imap://me@example.com/Archives/2023" },
+                    ]),
+                    "listAccounts" => json!([{
+                        "id": "account1",
+                        "name": "Work",
+                        "type": "imap",
+                        "identities": [
+                            { "id": "id1", "email": "me@example.com", "name": "Me" },
+                            { "id": "id2", "email": "sales@example.com", "name": "Sales" },
+                        ],
+                    }]),
+                    "searchContacts" => json!([
+                        { "id": "c1", "displayName": "Ann", "email": "ann@example.org" },
+                        { "id": "c2", "displayName": "Ann B", "email": "" },
+                    ]),
+                    _ => json!([]),
+                };
+                JsonRpcResponse::success(req.id, result)
+            }),
+            RetryPolicy::none(),
+        )
+    }
+
+    fn values(name: &str, value: &str) -> Vec<Value> {
+        let argument = Argument {
+            name: name.into(),
+            value: value.into(),
+            encoded: false,
+        };
+        let result = complete(&client(), &ToolPolicy::default(), &argument).unwrap();
+        result["completion"]["values"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn folders_match_path_or_name() {
+        assert_eq!(
+            values("moveTo", "arch"),
+            [
+                "imap://me@example.com/Archives",
+                "imap://me@example.com/Archives/2023"
+            ]
+        );
+        assert_eq!(
+            values("folderPath", "old"),
+            ["imap://me@example.com/Archives/2023"]
+        );
+        assert_eq!(values("folderPath", "").len(), 3);
+    }
+
+    #[test]
+    fn denied_sources_give_nothing() {
+        let tools = ToolPolicy::new(Profile::Full, vec![], vec!["searchContacts".into()]).unwrap();
+        let argument = Argument {
+            name: "to".into(),
+            value: "ann".into(),
+            encoded: false,
+        };
+        let result = complete(&client(), &tools, &argument).unwrap();
+        assert_eq!(result["completion"]["values"], json!([]));
+        assert_eq!(values("to", "ann"), [json!("ann@example.org")]);
+    }
+
+    #[test]
+    fn template_values_are_encoded() {
+        let argument = Argument {
+            name: "folderPath".into(),
+            value: "imap%3A%2F%2Fme%40example.com%2FArch".into(),
+            encoded: true,
+        };
+        let result = complete(&client(), &ToolPolicy::default(), &argument).unwrap();
+        assert_eq!(
+            result["completion"]["values"],
+            json!([
+                "imap%3A%2F%2Fme%40example.com%2FArchives",
+                "imap%3A%2F%2Fme%40example.com%2FArchives%2F2023"
+            ])
+        );
+    }
+
+    #[test]
+    fn identities_and_contacts() {
+        assert_eq!(values("from", "sal"), ["sales@example.com"]);
+        assert_eq!(values("to", "ann"), ["ann@example.org"]);
+        assert!(values("subject", "x").is_empty());
+    }
+
+    #[test]
+    fn reference_must_exist() {
+        let prompts = PromptLibrary::default();
+        let params = |reference: Value| json!({ "ref": reference, "argument": { "name": "folderPath", "value": "In" } });
+        let ok = argument(
+            &prompts,
+            Some(&params(
+                json!({ "type": "ref/prompt", "name": "draft-reply" }),
+            )),
+        )
+        .unwrap();
+        assert_eq!(ok.value, "In");
+        assert!(argument(
+            &prompts,
+            Some(&params(
+                json!({ "type": "ref/resource", "uri": MESSAGE_TEMPLATE })
+            ))
+        )
+        .is_ok());
+        let err = argument(
+            &prompts,
+            Some(&params(json!({ "type": "ref/prompt", "name": "nope" }))),
+        )
+        .unwrap_err();
+        assert_eq!(err.code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn long_lists_are_truncated() {
+        let result = completion((0..150).map(|i| i.to_string()).collect());
+        assert_eq!(
+            result["completion"]["values"].as_array().unwrap().len(),
+            MAX_VALUES
+        );
+        assert_eq!(result["completion"]["total"], 150);
+        assert_eq!(result["completion"]["hasMore"], true);
+    }
+}
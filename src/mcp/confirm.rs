@@ -0,0 +1,278 @@
+//! Asking the user before calls that take effect at once: `updateMessage`
+//! with `trash` or `moveTo`, and optionally compose tools with attachments.
+//!
+//! Clients that declare the `elicitation` capability get an
+//! `elicitation/create` request naming the message's sender and subject, and
+//! the call is only forwarded if the user accepts. Other clients cannot ask,
+//! so the call is allowed or refused according to the policy's fallback.
+//! A refused call is an `isError` result, so the agent learns why.
+
+use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::Outbox;
+use crate::audit::AuditStatus;
+use crate::client::ThunderbirdClient;
+use crate::model::GetMessageArgs;
+
+/// How long the user has to answer.
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// What to do when a call needs confirming and the client cannot ask.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fallback {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Fallback::Allow => "allow",
+            Fallback::Deny => "deny",
+        })
+    }
+}
+
+impl FromStr for Fallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "allow" => Ok(Fallback::Allow),
+            "deny" => Ok(Fallback::Deny),
+            other => Err(format!(
+                "Unknown confirm fallback: {} (expected allow or deny)",
+                other
+            )),
+        }
+    }
+}
+
+/// Which calls need the user's confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmPolicy {
+    /// `updateMessage` moving a message to Trash or another folder.
+    pub destructive: bool,
+    /// `sendMail`, `replyToMessage` and `forwardMessage` with attachments.
+    pub attachments: bool,
+    pub fallback: Fallback,
+}
+
+impl Default for ConfirmPolicy {
+    fn default() -> Self {
+        Self {
+            destructive: true,
+            attachments: false,
+            fallback: Fallback::Allow,
+        }
+    }
+}
+
+impl ConfirmPolicy {
+    /// Whether `tool` running with `args` needs the user's confirmation.
+    /// Unlike [`ConfirmPolicy::question`] this asks Thunderbird nothing.
+    pub fn applies(&self, tool: &str, args: &Value) -> bool {
+        match tool {
+            "updateMessage" if self.destructive => {
+                args.get("trash") == Some(&Value::Bool(true))
+                    || args
+                        .get("moveTo")
+                        .and_then(Value::as_str)
+                        .is_some_and(|folder| !folder.is_empty())
+            }
+            "sendMail" | "replyToMessage" | "forwardMessage" if self.attachments => {
+                !files(args).is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// What to ask the user before `tool` runs with `args`, if anything.
+    pub fn question(&self, client: &ThunderbirdClient, tool: &str, args: &Value) -> Option<String> {
+        if !self.applies(tool, args) {
+            return None;
+        }
+        let text = |name: &str| args.get(name).and_then(Value::as_str).unwrap_or("");
+        match tool {
+            "updateMessage" => {
+                let action = if args.get("trash") == Some(&Value::Bool(true)) {
+                    "Move this message to Trash?".to_string()
+                } else {
+                    format!("Move this message to {}?", text("moveTo"))
+                };
+                Some(format!("{}\n\n{}", action, summary(client, args)))
+            }
+            _ => {
+                let about = match tool {
+                    "sendMail" => format!("To: {}\nSubject: {}", text("to"), text("subject")),
+                    _ => summary(client, args),
+                };
+                Some(format!(
+                    "Attach these files to a {}?\n\n{}\n\n{}",
+                    match tool {
+                        "replyToMessage" => "reply",
+                        "forwardMessage" => "forwarded message",
+                        _ => "new message",
+                    },
+                    files(args).join("\n"),
+                    about
+                ))
+            }
+        }
+    }
+
+    /// `Ok` if `tool` may run, else why not, as `Refused` by the fallback or
+    /// `Declined` once the user was asked. Stops waiting for the user once
+    /// `cancelled` returns true.
+    pub(crate) fn check(
+        &self,
+        client: &ThunderbirdClient,
+        outbox: &Outbox,
+        can_elicit: bool,
+        tool: &str,
+        args: &Value,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<(), (AuditStatus, String)> {
+        if !self.applies(tool, args) {
+            return Ok(());
+        }
+        // Decided before the question, whose summary fetches the message
+        if !can_elicit {
+            return match self.fallback {
+                Fallback::Allow => Ok(()),
+                Fallback::Deny => Err((
+                    AuditStatus::Refused,
+                    format!(
+                        "{} needs the user's confirmation, which this MCP client cannot ask for",
+                        tool
+                    ),
+                )),
+            };
+        }
+        let Some(question) = self.question(client, tool, args) else {
+            return Ok(());
+        };
+
+        let params = json!({
+            "message": question,
+            "requestedSchema": {
+                "type": "object",
+                "properties": {
+                    "confirm": { "type": "boolean", "title": "Go ahead", "default": false }
+                },
+                "required": ["confirm"]
+            }
+        });
+        let declined = |reason: String| (AuditStatus::Declined, reason);
+        let answer = outbox
+            .request("elicitation/create", params, CONFIRM_TIMEOUT, cancelled)
+            .map_err(|e| {
+                declined(format!(
+                    "Could not ask the user to confirm {}: {}",
+                    tool, e.message
+                ))
+            })?;
+        let action = answer.get("action").and_then(Value::as_str).unwrap_or("");
+        let confirmed = answer.pointer("/content/confirm") == Some(&Value::Bool(true));
+        match (action, confirmed) {
+            ("accept", true) => Ok(()),
+            ("accept", false) => Err(declined(format!("The user did not confirm {}", tool))),
+            _ => Err(declined(format!("The user declined {}", tool))),
+        }
+    }
+}
+
+/// The attachment paths in `args`.
+fn files(args: &Value) -> Vec<&str> {
+    args.get("attachments")
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Sender and subject of the message in `args`, for the user to recognize.
+fn summary(client: &ThunderbirdClient, args: &Value) -> String {
+    let text = |name: &str| args.get(name).and_then(Value::as_str).unwrap_or("");
+    let message = client.get_message(&GetMessageArgs {
+        message_id: text("messageId").into(),
+        folder_path: text("folderPath").into(),
+        save_attachments: false,
+    });
+    match message {
+        Ok(m) => format!("From: {}\nSubject: {}", m.author, m.subject),
+        // The call itself will report the problem
+        Err(_) => format!("Message {} in {}", text("messageId"), text("folderPath")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::transport::MemoryTransport;
+    use crate::types::JsonRpcResponse;
+
+    fn client() -> ThunderbirdClient {
+        ThunderbirdClient::with_transport(
+            MemoryTransport::new(|req| {
+                let message = json!({
+                    "id": "a@example.com",
+                    "subject": "Invoice",
+                    "author": "Bob <bob@example.com>",
+                });
+                JsonRpcResponse::success(req.id, message)
+            }),
+            RetryPolicy::none(),
+        )
+    }
+
+    #[test]
+    fn questions() {
+        let policy = ConfirmPolicy::default();
+        let trash = json!({ "messageId": "a@example.com", "folderPath": "f", "trash": true });
+        assert_eq!(
+            policy.question(&client(), "updateMessage", &trash).unwrap(),
+            "Move this message to Trash?\n\nFrom: Bob <bob@example.com>\nSubject: Invoice"
+        );
+        let read = json!({ "messageId": "a@example.com", "folderPath": "f", "read": true });
+        assert_eq!(policy.question(&client(), "updateMessage", &read), None);
+
+        let attach = json!({ "to": "x@example.com", "attachments": ["/tmp/a.pdf"] });
+        assert_eq!(policy.question(&client(), "sendMail", &attach), None);
+        let policy = ConfirmPolicy {
+            attachments: true,
+            ..policy
+        };
+        assert!(policy
+            .question(&client(), "sendMail", &attach)
+            .unwrap()
+            .contains("/tmp/a.pdf\n\nTo: x@example.com"));
+    }
+
+    #[test]
+    fn fallback_without_elicitation() {
+        let trash = json!({ "messageId": "a@example.com", "folderPath": "f", "trash": true });
+        let outbox = Outbox::new(|_| panic!("nothing is sent"));
+        // The fallback needs no summary of the message
+        let client = ThunderbirdClient::with_transport(
+            MemoryTransport::new(|req| panic!("{} was called", req.method)),
+            RetryPolicy::none(),
+        );
+        let never = || false;
+        let allow = ConfirmPolicy::default();
+        assert!(allow
+            .check(&client, &outbox, false, "updateMessage", &trash, &never)
+            .is_ok());
+        let deny = ConfirmPolicy {
+            fallback: "deny".parse().unwrap(),
+            ..allow
+        };
+        assert!(deny
+            .check(&client, &outbox, false, "updateMessage", &trash, &never)
+            .is_err());
+    }
+}
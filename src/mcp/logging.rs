@@ -0,0 +1,227 @@
+//! The MCP `logging` capability: `logging/setLevel` and `notifications/message`.
+//!
+//! Many MCP clients hide the bridge's stderr, so connection failures,
+//! sanitized responses, retries and slow calls are also sent to the client.
+//! Every message is written to stderr as a JSON line too, with the same
+//! fields. Both start at the level in `THUNDERBIRD_API_LOG` (default
+//! `warning`, or `off`); `logging/setLevel` only changes what the client gets.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::Outbox;
+use crate::client::ClientEvent;
+use crate::config::LOG_ENV;
+use crate::error::{Result, ThunderbirdError};
+
+pub const DEFAULT_LEVEL: LogLevel = LogLevel::Warning;
+
+/// Calls that take longer are logged as warnings.
+pub const SLOW_CALL: Duration = Duration::from_secs(5);
+
+/// Name the bridge logs under, as `logger` in `notifications/message`.
+const LOGGER: &str = "thunderbird-api";
+
+/// Syslog severities, as MCP uses them. Ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Notice => "notice",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+            LogLevel::Critical => "critical",
+            LogLevel::Alert => "alert",
+            LogLevel::Emergency => "emergency",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "notice" => Ok(LogLevel::Notice),
+            "warning" | "warn" => Ok(LogLevel::Warning),
+            "error" => Ok(LogLevel::Error),
+            "critical" => Ok(LogLevel::Critical),
+            "alert" => Ok(LogLevel::Alert),
+            "emergency" => Ok(LogLevel::Emergency),
+            other => Err(format!("Unknown log level: {}", other)),
+        }
+    }
+}
+
+/// Sends log messages to the MCP client and to stderr, each with its own
+/// minimum level. `None` turns a destination off.
+pub struct Logger {
+    outbox: Outbox,
+    client_level: Mutex<Option<LogLevel>>,
+    stderr_level: Option<LogLevel>,
+}
+
+impl Logger {
+    pub fn new(outbox: Outbox, level: Option<LogLevel>) -> Self {
+        Self {
+            outbox,
+            client_level: Mutex::new(level),
+            stderr_level: level,
+        }
+    }
+
+    /// Level from `THUNDERBIRD_API_LOG`: a level name or `off`.
+    pub fn from_env(outbox: Outbox) -> Result<Self> {
+        let level = match env::var(LOG_ENV) {
+            Err(_) => Some(DEFAULT_LEVEL),
+            Ok(v) if v.trim().is_empty() => Some(DEFAULT_LEVEL),
+            Ok(v) if v.trim().eq_ignore_ascii_case("off") => None,
+            Ok(v) => Some(
+                v.parse()
+                    .map_err(|e| ThunderbirdError::Config(format!("Invalid {}: {}", LOG_ENV, e)))?,
+            ),
+        };
+        Ok(Self::new(outbox, level))
+    }
+
+    /// `logging/setLevel`. Affects only what the client is sent.
+    pub fn set_level(&self, level: LogLevel) {
+        *self.client_level.lock().unwrap_or_else(|e| e.into_inner()) = Some(level);
+    }
+
+    pub fn log(&self, level: LogLevel, data: Value) {
+        let params = json!({ "level": level, "logger": LOGGER, "data": data });
+        if self.stderr_level.is_some_and(|min| level >= min) {
+            eprintln!("{}", params);
+        }
+        let client_level = *self.client_level.lock().unwrap_or_else(|e| e.into_inner());
+        if client_level.is_some_and(|min| level >= min) {
+            self.outbox.notify("notifications/message", params);
+        }
+    }
+
+    /// Log what the client reports; see [`ThunderbirdClient::on_event`](crate::client::ThunderbirdClient::on_event).
+    pub fn client_event(&self, event: &ClientEvent) {
+        match *event {
+            ClientEvent::Retrying {
+                method,
+                delay,
+                error,
+            } => self.log(
+                LogLevel::Info,
+                json!({
+                    "message": format!("Retrying {} in {} ms: {}", method, delay.as_millis(), error),
+                    "method": method,
+                }),
+            ),
+            ClientEvent::Sanitized { method, lossy } => self.log(
+                LogLevel::Warning,
+                json!({
+                    "message": if lossy {
+                        format!("{} response had invalid UTF-8, replaced with U+FFFD", method)
+                    } else {
+                        format!("{} response had raw control characters, removed", method)
+                    },
+                    "method": method,
+                }),
+            ),
+            ClientEvent::Failed { method, error } => self.log(
+                LogLevel::Error,
+                json!({
+                    "message": format!("{} failed: {}", method, error),
+                    "method": method,
+                }),
+            ),
+            ClientEvent::AuditFailed { method, error } => self.log(
+                LogLevel::Error,
+                json!({
+                    "message": format!("{} was not audited: {}", method, error),
+                    "method": method,
+                }),
+            ),
+            ClientEvent::Completed { method, elapsed } => {
+                let level = if elapsed >= SLOW_CALL {
+                    LogLevel::Warning
+                } else {
+                    LogLevel::Debug
+                };
+                let data = json!({
+                    "message": format!("{} took {} ms", method, elapsed.as_millis()),
+                    "method": method,
+                    "elapsedMs": elapsed.as_millis() as u64,
+                });
+                self.log(level, data);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::JsonRpcRequest;
+    use std::sync::Arc;
+
+    fn logger(level: Option<LogLevel>) -> (Logger, Arc<Mutex<Vec<JsonRpcRequest>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&sent);
+        let outbox = Outbox::new(move |m| sink.lock().unwrap().push(m.clone()));
+        (Logger::new(outbox, level), sent)
+    }
+
+    #[test]
+    fn levels_are_ordered() {
+        assert!(LogLevel::Debug < LogLevel::Warning);
+        assert_eq!("WARN".parse::<LogLevel>(), Ok(LogLevel::Warning));
+        assert!("loud".parse::<LogLevel>().is_err());
+    }
+
+    #[test]
+    fn set_level_filters_client_messages() {
+        let (logger, sent) = logger(None);
+        logger.log(LogLevel::Emergency, json!("dropped"));
+        logger.set_level(LogLevel::Info);
+        logger.client_event(&ClientEvent::Completed {
+            method: "listFolders",
+            elapsed: Duration::from_millis(20),
+        });
+        logger.client_event(&ClientEvent::Completed {
+            method: "searchMessages",
+            elapsed: SLOW_CALL,
+        });
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        let params = sent[0].params.as_ref().unwrap();
+        assert_eq!(sent[0].method, "notifications/message");
+        assert_eq!(params["level"], "warning");
+        assert_eq!(params["data"]["method"], "searchMessages");
+    }
+}
@@ -0,0 +1,832 @@
+//! MCP server side of the `thunderbird-api` stdio bridge: session lifecycle,
+//! methods answered locally, and translation of `tools/list` and `tools/call`
+//! to the extension's direct JSON-RPC protocol.
+
+pub mod annotations;
+pub mod completion;
+pub mod confirm;
+pub mod logging;
+mod output;
+pub mod pool;
+pub mod profile;
+pub mod progress;
+pub mod prompts;
+pub mod resources;
+pub mod subscriptions;
+
+pub use output::{output_schema, STRUCTURED_OUTPUT_VERSION};
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::audit::{AuditEntry, AuditLog, AuditStatus};
+use crate::client::{ClientEvent, ThunderbirdClient};
+use crate::error::{INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND};
+use crate::types::{JsonRpcError, JsonRpcRequest, JsonRpcResponse};
+use annotations::{ToolAnnotations, ANNOTATIONS_VERSION};
+use confirm::ConfirmPolicy;
+use logging::{LogLevel, Logger, DEFAULT_LEVEL};
+use pool::{InFlight, Job, Work};
+use profile::ToolPolicy;
+use prompts::PromptLibrary;
+use subscriptions::{Subscriptions, DEFAULT_POLL_INTERVAL};
+
+/// MCP revisions the bridge speaks, newest first.
+pub const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+pub const SERVER_NAME: &str = "thunderbird-bridge";
+
+/// Where a session is in the MCP lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    /// Waiting for `initialize`. Only `ping` is answered.
+    Uninitialized,
+    /// `initialize` was answered; waiting for `notifications/initialized`.
+    /// Requests are already served, since some clients do not wait.
+    Initializing,
+    /// The client sent `notifications/initialized`.
+    Ready,
+}
+
+/// The newest version both sides support. MCP has the server answer with its
+/// own latest version when it does not support the requested one; the client
+/// then decides whether to disconnect.
+pub fn negotiate_version(requested: Option<&str>) -> &'static str {
+    requested
+        .and_then(|v| PROTOCOL_VERSIONS.iter().find(|&&s| s == v))
+        .unwrap_or(&PROTOCOL_VERSIONS[0])
+}
+
+/// Where the bridge writes messages it sends on its own initiative, such as
+/// notifications and requests to the client. Called from background threads.
+#[derive(Clone)]
+pub struct Outbox {
+    send: Arc<dyn Fn(&JsonRpcRequest) + Send + Sync>,
+    /// Requests to the client waiting for a response, by id.
+    pending: Arc<Mutex<HashMap<String, mpsc::Sender<JsonRpcResponse>>>>,
+    /// The client is gone, so nothing it was asked will be answered.
+    closed: Arc<AtomicBool>,
+}
+
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
+/// How often a request to the client checks whether to stop waiting.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+impl Outbox {
+    pub fn new(send: impl Fn(&JsonRpcRequest) + Send + Sync + 'static) -> Self {
+        Self {
+            send: Arc::new(send),
+            pending: Arc::default(),
+            closed: Arc::default(),
+        }
+    }
+
+    pub fn notify(&self, method: &str, params: Value) {
+        (self.send)(&JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            id: None,
+            method: method.into(),
+            params: Some(params),
+        });
+    }
+
+    /// Send a request to the client and wait up to `timeout` for the result,
+    /// which arrives through [`Outbox::deliver`]. Stops waiting once
+    /// `cancelled` returns true, telling the client with
+    /// `notifications/cancelled`, or once the outbox is closed.
+    pub fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<Value, JsonRpcError> {
+        let id = format!(
+            "{}-{}",
+            SERVER_NAME,
+            NEXT_REQUEST.fetch_add(1, Ordering::SeqCst)
+        );
+        let (answer, answered) = mpsc::channel();
+        self.pending().insert(id.clone(), answer);
+        (self.send)(&JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            id: Some(json!(id)),
+            method: method.into(),
+            params: Some(params),
+        });
+        let deadline = Instant::now() + timeout;
+        let response = loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match answered.recv_timeout(left.min(CANCEL_POLL)) {
+                Err(RecvTimeoutError::Timeout) if left > CANCEL_POLL => {}
+                outcome => break outcome,
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                break Err(RecvTimeoutError::Disconnected);
+            }
+            if cancelled() {
+                self.notify(
+                    "notifications/cancelled",
+                    json!({ "requestId": id, "reason": "The request that needed it was cancelled" }),
+                );
+                break Err(RecvTimeoutError::Disconnected);
+            }
+        };
+        self.pending().remove(&id);
+        match response {
+            Ok(JsonRpcResponse { error: Some(e), .. }) => Err(e),
+            Ok(response) => Ok(response.result.unwrap_or(Value::Null)),
+            Err(RecvTimeoutError::Timeout) => Err(JsonRpcError {
+                code: INTERNAL_ERROR,
+                message: format!("No response to {} within {} s", method, timeout.as_secs()),
+            }),
+            Err(RecvTimeoutError::Disconnected) => Err(JsonRpcError {
+                code: INTERNAL_ERROR,
+                message: format!("Stopped waiting for a response to {}", method),
+            }),
+        }
+    }
+
+    /// Stop every request waiting for the client, which has disconnected.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    /// Pass a response from the client to the request waiting for it.
+    /// Returns false if nothing is waiting, e.g. after a timeout.
+    pub fn deliver(&self, response: JsonRpcResponse) -> bool {
+        let id = match &response.id {
+            Some(Value::String(id)) => id.clone(),
+            _ => return false,
+        };
+        match self.pending().remove(&id) {
+            Some(waiting) => waiting.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    fn pending(&self) -> MutexGuard<'_, HashMap<String, mpsc::Sender<JsonRpcResponse>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// What [`Bridge::dispatch`] did with a message.
+pub enum Dispatch {
+    /// Answered on the spot; `None` for notifications.
+    Answered(Option<JsonRpcResponse>),
+    /// Needs the extension. Run the job, usually on a [`pool::WorkerPool`], and
+    /// write its response if it has one.
+    Deferred(Job),
+}
+
+/// One MCP session over stdio, forwarding tool calls to Thunderbird.
+pub struct Bridge {
+    client: Arc<ThunderbirdClient>,
+    outbox: Outbox,
+    lifecycle: Lifecycle,
+    protocol_version: Option<&'static str>,
+    subscriptions: Arc<Subscriptions>,
+    prompts: PromptLibrary,
+    in_flight: InFlight,
+    logger: Arc<Logger>,
+    tools: Arc<ToolPolicy>,
+    annotations: Arc<ToolAnnotations>,
+    confirm: ConfirmPolicy,
+    /// The client declared the `elicitation` capability.
+    elicitation: bool,
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl Bridge {
+    pub fn new(client: ThunderbirdClient, outbox: Outbox) -> Self {
+        Self {
+            client: Arc::new(client),
+            lifecycle: Lifecycle::Uninitialized,
+            protocol_version: None,
+            subscriptions: Arc::new(Subscriptions::new(DEFAULT_POLL_INTERVAL)),
+            prompts: PromptLibrary::default(),
+            in_flight: InFlight::default(),
+            logger: Arc::new(Logger::new(outbox.clone(), Some(DEFAULT_LEVEL))),
+            tools: Arc::new(ToolPolicy::default()),
+            annotations: Arc::new(ToolAnnotations::default()),
+            confirm: ConfirmPolicy::default(),
+            elicitation: false,
+            audit: None,
+            outbox,
+        }
+    }
+
+    /// Record every `tools/call` in `log`. The bridge's own lookups, such as
+    /// subscription polls, completions and confirmation summaries, are not
+    /// recorded, so the client should not write to a log as well; see
+    /// [`ThunderbirdClient::take_audit_log`].
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.audit = Some(Arc::new(log));
+        self
+    }
+
+    /// Where `logging/setLevel` applies. Share it with the client's
+    /// [`on_event`](ThunderbirdClient::on_event) to log its calls.
+    pub fn logger(mut self, logger: Arc<Logger>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Tools offered by `tools/list` and accepted by `tools/call`; all of
+    /// them by default.
+    pub fn tool_policy(mut self, policy: ToolPolicy) -> Self {
+        self.tools = Arc::new(policy);
+        self
+    }
+
+    /// Hints added to `tools/list`; the built-in table by default.
+    pub fn tool_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Arc::new(annotations);
+        self
+    }
+
+    /// Which calls the user is asked to confirm.
+    pub fn confirm_policy(mut self, policy: ConfirmPolicy) -> Self {
+        self.confirm = policy;
+        self
+    }
+
+    /// Prompts offered by `prompts/list`; the built-in ones by default.
+    pub fn prompts(mut self, prompts: PromptLibrary) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// How often subscribed folders are checked for new mail.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.subscriptions = Arc::new(Subscriptions::new(interval));
+        self
+    }
+
+    pub fn lifecycle(&self) -> Lifecycle {
+        self.lifecycle
+    }
+
+    /// Version agreed on in `initialize`, if it has happened.
+    pub fn protocol_version(&self) -> Option<&'static str> {
+        self.protocol_version
+    }
+
+    /// Whether the client understands tool `annotations`.
+    fn annotated_tools(&self) -> bool {
+        self.protocol_version
+            .is_some_and(|v| v >= ANNOTATIONS_VERSION)
+    }
+
+    /// Whether the client understands `outputSchema` and `structuredContent`.
+    /// Versions are dates, so they compare as strings.
+    fn structured_output(&self) -> bool {
+        self.protocol_version
+            .is_some_and(|v| v >= STRUCTURED_OUTPUT_VERSION)
+    }
+
+    /// A response from the client to a request the bridge sent, such as
+    /// `elicitation/create`. Returns false if nothing waits for it, e.g. after
+    /// a timeout or for an id the bridge never sent.
+    pub fn client_response(&self, response: JsonRpcResponse) -> bool {
+        self.outbox.deliver(response)
+    }
+
+    /// The client closed its end. Calls waiting for the user to confirm are
+    /// refused instead of waiting for an answer that cannot come.
+    pub fn disconnect(&self) {
+        self.outbox.close();
+    }
+
+    /// Handle one message from the client, waiting for the extension if needed.
+    /// Notifications and cancelled requests produce no response.
+    pub fn handle(&mut self, request: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+        match self.dispatch(request) {
+            Dispatch::Answered(response) => response,
+            Dispatch::Deferred(job) => job.run(),
+        }
+    }
+
+    /// Handle one message from the client, leaving calls to the extension for
+    /// the caller to run. Messages must be dispatched in the order they arrive.
+    pub fn dispatch(&mut self, request: &JsonRpcRequest) -> Dispatch {
+        // Requests without an id are notifications
+        let Some(id) = request.id.clone() else {
+            self.notify(request);
+            return Dispatch::Answered(None);
+        };
+
+        // Responses and cancellations could not tell the two requests apart
+        if self.in_flight.contains(&id) {
+            return Dispatch::Answered(Some(JsonRpcResponse::error(
+                Some(id.clone()),
+                INVALID_REQUEST,
+                format!("Request id {} is already in use by a pending request", id),
+            )));
+        }
+
+        if self.lifecycle != Lifecycle::Uninitialized {
+            if request.method == "tools/call" {
+                let name = request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("name"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("");
+                if let Err(e) = self.tools.check(name) {
+                    if let Some(audit) = self.audit() {
+                        let arguments = request.params.as_ref().and_then(|p| p.get("arguments"));
+                        let status = AuditStatus::Refused;
+                        audit.not_sent(name, arguments, status, &e.message, Duration::ZERO);
+                    }
+                    return Dispatch::Answered(Some(respond(id, Err(e))));
+                }
+            }
+            if let Some(work) = self.extension_call(request) {
+                let job = Job::new(id, &self.in_flight, work);
+                return Dispatch::Deferred(match self.asks_user(request) {
+                    true => job.waiting_for_user(),
+                    false => job,
+                });
+            }
+        }
+
+        let response = match (request.method.as_str(), self.lifecycle) {
+            // Ping is allowed at any point, even before initialize
+            ("ping", _) => JsonRpcResponse::success(Some(id), json!({})),
+            ("initialize", Lifecycle::Uninitialized) => self.initialize(id, request),
+            ("initialize", _) => JsonRpcResponse::error(
+                Some(id),
+                INVALID_REQUEST,
+                "Session is already initialized".into(),
+            ),
+            (method, Lifecycle::Uninitialized) => JsonRpcResponse::error(
+                Some(id),
+                INVALID_REQUEST,
+                format!("Received {} before initialize", method),
+            ),
+            ("resources/templates/list", _) => {
+                JsonRpcResponse::success(Some(id), resources::templates())
+            }
+            ("resources/unsubscribe", _) => {
+                let uri = param_str(request, "uri");
+                respond(id, uri.and_then(|uri| self.subscriptions.unsubscribe(uri)))
+            }
+            ("logging/setLevel", _) => {
+                let level = param_str(request, "level").and_then(|level| {
+                    level.parse::<LogLevel>().map_err(|message| JsonRpcError {
+                        code: INVALID_PARAMS,
+                        message,
+                    })
+                });
+                respond(
+                    id,
+                    level.map(|level| {
+                        self.logger.set_level(level);
+                        json!({})
+                    }),
+                )
+            }
+            ("prompts/list", _) => JsonRpcResponse::success(Some(id), self.prompts.list()),
+            ("prompts/get", _) => {
+                let name = param_str(request, "name");
+                let arguments = request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("arguments"))
+                    .and_then(Value::as_object)
+                    .cloned()
+                    .unwrap_or_default();
+                respond(
+                    id,
+                    name.and_then(|name| self.prompts.render(name, &arguments)),
+                )
+            }
+            (method, _) => JsonRpcResponse::error(
+                Some(id),
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            ),
+        };
+        Dispatch::Answered(Some(response))
+    }
+
+    /// Requests answered by calling the extension, as work that can run on
+    /// another thread.
+    fn extension_call(&self, request: &JsonRpcRequest) -> Option<Work> {
+        let client = Arc::clone(&self.client);
+        let request = request.clone();
+        let id = request.id.clone().unwrap_or_default();
+        let work: Work = match request.method.clone().as_str() {
+            "tools/list" => {
+                let structured = self.structured_output();
+                let tools = Arc::clone(&self.tools);
+                let annotations = self
+                    .annotated_tools()
+                    .then(|| Arc::clone(&self.annotations));
+                Box::new(move || {
+                    let mut response =
+                        forward_to_extension(&client, structured, &request, None, None);
+                    if let Some(result) = response.result.as_mut() {
+                        tools.filter_list(result);
+                        if let Some(annotations) = &annotations {
+                            annotations.annotate(result);
+                        }
+                    }
+                    response
+                })
+            }
+            "tools/call" => {
+                let structured = self.structured_output();
+                let outbox = self.outbox.clone();
+                let (confirm, elicitation) = (self.confirm, self.elicitation);
+                let in_flight = self.in_flight.clone();
+                let audit = self.audit();
+                Box::new(move || {
+                    let params = request.params.as_ref();
+                    let name = params
+                        .and_then(|p| p.get("name"))
+                        .and_then(Value::as_str)
+                        .unwrap_or("");
+                    let arguments = params.and_then(|p| p.get("arguments"));
+                    let arguments = arguments.cloned().unwrap_or(json!({}));
+                    let cancelled = || in_flight.is_cancelled(&id);
+                    let started = Instant::now();
+                    if let Err((status, reason)) =
+                        confirm.check(&client, &outbox, elicitation, name, &arguments, &cancelled)
+                    {
+                        if let Some(audit) = &audit {
+                            let elapsed = started.elapsed();
+                            audit.not_sent(name, Some(&arguments), status, &reason, elapsed);
+                        }
+                        return JsonRpcResponse::success(Some(id), output::error_result(&reason));
+                    }
+                    let Some(token) = progress::request_token(request.params.as_ref()) else {
+                        return forward_to_extension(
+                            &client,
+                            structured,
+                            &request,
+                            None,
+                            audit.as_ref(),
+                        );
+                    };
+                    let ext_token = progress::extension_token();
+                    progress::with_progress(&client, &outbox, token, &ext_token, || {
+                        forward_to_extension(
+                            &client,
+                            structured,
+                            &request,
+                            Some(&ext_token),
+                            audit.as_ref(),
+                        )
+                    })
+                })
+            }
+            "completion/complete" => {
+                let argument = completion::argument(&self.prompts, request.params.as_ref());
+                let tools = Arc::clone(&self.tools);
+                Box::new(move || {
+                    let result = argument.and_then(|a| completion::complete(&client, &tools, &a));
+                    respond(id, result)
+                })
+            }
+            "resources/list" => {
+                let tools = Arc::clone(&self.tools);
+                Box::new(move || respond(id, resources::list(&client, &tools)))
+            }
+            "resources/read" => {
+                let tools = Arc::clone(&self.tools);
+                Box::new(move || {
+                    let uri = param_str(&request, "uri");
+                    let result = uri.and_then(|uri| resources::read(&client, &tools, uri));
+                    respond(id, result)
+                })
+            }
+            "resources/subscribe" => {
+                // Recorded now, so a later unsubscribe cannot overtake it
+                let registration = param_str(&request, "uri")
+                    .and_then(|uri| self.subscriptions.register(&client, &self.outbox, uri));
+                let subscriptions = Arc::clone(&self.subscriptions);
+                Box::new(move || {
+                    let result = registration
+                        .and_then(|registration| subscriptions.baseline(&client, registration));
+                    respond(id, result)
+                })
+            }
+            _ => return None,
+        };
+        Some(work)
+    }
+
+    /// Whether `request` may wait for the user to confirm it, which can take
+    /// minutes. Such calls do not take one of the pool's workers.
+    fn asks_user(&self, request: &JsonRpcRequest) -> bool {
+        let params = request.params.as_ref();
+        let name = params.and_then(|p| p.get("name")).and_then(Value::as_str);
+        let arguments = params.and_then(|p| p.get("arguments"));
+        match (request.method.as_str(), name, arguments) {
+            ("tools/call", Some(name), Some(arguments)) => {
+                self.elicitation && self.confirm.applies(name, arguments)
+            }
+            _ => false,
+        }
+    }
+
+    /// Where to record tool calls, if anywhere.
+    fn audit(&self) -> Option<Audit> {
+        self.audit.clone().map(|log| Audit {
+            log,
+            logger: Arc::clone(&self.logger),
+        })
+    }
+
+    fn notify(&mut self, notification: &JsonRpcRequest) {
+        match notification.method.as_str() {
+            "notifications/initialized" if self.lifecycle == Lifecycle::Initializing => {
+                self.lifecycle = Lifecycle::Ready;
+            }
+            "notifications/cancelled" => {
+                let request_id = notification
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("requestId"));
+                if let Some(request_id) = request_id {
+                    self.in_flight.cancel(request_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn initialize(&mut self, id: Value, request: &JsonRpcRequest) -> JsonRpcResponse {
+        let requested = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(Value::as_str);
+        let version = negotiate_version(requested);
+        self.protocol_version = Some(version);
+        self.elicitation = request
+            .params
+            .as_ref()
+            .and_then(|p| p.pointer("/capabilities/elicitation"))
+            .is_some_and(Value::is_object);
+        self.lifecycle = Lifecycle::Initializing;
+
+        JsonRpcResponse::success(
+            Some(id),
+            json!({
+                "protocolVersion": version,
+                "capabilities": {
+                    "tools": {},
+                    "resources": { "subscribe": true },
+                    "prompts": {},
+                    "logging": {},
+                    "completions": {}
+                },
+                "serverInfo": { "name": SERVER_NAME, "version": env!("CARGO_PKG_VERSION") }
+            }),
+        )
+    }
+}
+
+/// Translate MCP tools/list and tools/call to the extension's direct JSON-RPC protocol.
+/// `progress_token` is passed on for the extension to report progress under.
+/// Tool calls are recorded in `audit`, if given.
+fn forward_to_extension(
+    client: &ThunderbirdClient,
+    structured: bool,
+    request: &JsonRpcRequest,
+    progress_token: Option<&str>,
+    audit: Option<&Audit>,
+) -> JsonRpcResponse {
+    match request.method.as_str() {
+        "tools/list" => {
+            let ext_request = JsonRpcRequest {
+                jsonrpc: "2.0".into(),
+                id: request.id.clone(),
+                method: "listTools".into(),
+                params: None,
+            };
+            let (mut response, _) = send(client, &ext_request, None);
+            if let Some(result) = response.result.as_mut().filter(|_| structured) {
+                output::add_output_schemas(result);
+            }
+            response
+        }
+        _ => {
+            let params = request.params.as_ref();
+            let name = params
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .unwrap_or("");
+            let mut arguments = params
+                .and_then(|p| p.get("arguments"))
+                .cloned()
+                .unwrap_or(json!({}));
+            if let (Some(token), Some(arguments)) = (progress_token, arguments.as_object_mut()) {
+                arguments.insert("_meta".into(), json!({ "progressToken": token }));
+            }
+
+            let ext_request = JsonRpcRequest {
+                jsonrpc: "2.0".into(),
+                id: request.id.clone(),
+                method: name.into(),
+                params: Some(arguments),
+            };
+            let (ext_response, lossy) = send(client, &ext_request, audit);
+
+            // Wrap result in MCP content blocks for the MCP client
+            if let Some(result) = ext_response.result {
+                let mut content = output::tool_result(name, result, structured);
+                // Tell the client that parts of the text are U+FFFD, not the original bytes
+                if lossy {
+                    content["_meta"] = json!({ "thunderbird-api/lossyUtf8": true });
+                }
+                JsonRpcResponse::success(ext_response.id, content)
+            } else {
+                ext_response
+            }
+        }
+    }
+}
+
+/// Send to the extension, reporting transport failures as a JSON-RPC error,
+/// and tell whether invalid UTF-8 in the answer was replaced. The response
+/// always carries the MCP request's id, even when replayed from a cassette.
+fn send(
+    client: &ThunderbirdClient,
+    request: &JsonRpcRequest,
+    audit: Option<&Audit>,
+) -> (JsonRpcResponse, bool) {
+    let start = Instant::now();
+    let (result, lossy) = match client.send_raw_lossy(request) {
+        Ok((response, lossy)) => (Ok(response), lossy),
+        Err(e) => (Err(e), false),
+    };
+    if let Some(audit) = audit {
+        audit.record(request, &result, start.elapsed());
+    }
+    let response = match result {
+        Ok(mut response) => {
+            response.id = request.id.clone();
+            response
+        }
+        Err(e) => e.to_response(request.id.clone()),
+    };
+    (response, lossy)
+}
+
+/// The bridge's audit log, with failures to write it reported to the logger.
+struct Audit {
+    log: Arc<AuditLog>,
+    logger: Arc<Logger>,
+}
+
+impl Audit {
+    fn record(
+        &self,
+        request: &JsonRpcRequest,
+        result: &crate::error::Result<JsonRpcResponse>,
+        elapsed: Duration,
+    ) {
+        let Some(entry) = AuditEntry::new(request, result, elapsed) else {
+            return;
+        };
+        self.write(&request.method, entry);
+    }
+
+    /// Record a tool call that was refused or declined before it was sent.
+    fn not_sent(
+        &self,
+        tool: &str,
+        arguments: Option<&Value>,
+        status: AuditStatus,
+        reason: &str,
+        elapsed: Duration,
+    ) {
+        let entry = AuditEntry::not_sent(tool, arguments, status, reason, elapsed);
+        self.write(tool, entry);
+    }
+
+    fn write(&self, method: &str, entry: AuditEntry) {
+        if let Err(error) = self.log.write(entry) {
+            self.logger.client_event(&ClientEvent::AuditFailed {
+                method,
+                error: &error,
+            });
+        }
+    }
+}
+
+fn respond(id: Value, result: Result<Value, JsonRpcError>) -> JsonRpcResponse {
+    match result {
+        Ok(result) => JsonRpcResponse::success(Some(id), result),
+        Err(e) => JsonRpcResponse::error(Some(id), e.code, e.message),
+    }
+}
+
+/// A required string parameter.
+fn param_str<'a>(request: &'a JsonRpcRequest, name: &str) -> Result<&'a str, JsonRpcError> {
+    request
+        .params
+        .as_ref()
+        .and_then(|p| p.get(name))
+        .and_then(Value::as_str)
+        .ok_or_else(|| JsonRpcError {
+            code: INVALID_PARAMS,
+            message: format!("Missing {} parameter", name),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::transport::MemoryTransport;
+
+    fn bridge() -> Bridge {
+        Bridge::new(
+            ThunderbirdClient::with_transport(
+                MemoryTransport::new(|req| {
+                    JsonRpcResponse::success(req.id, json!({ "tools": [] }))
+                }),
+                RetryPolicy::none(),
+            ),
+            Outbox::new(|_| {}),
+        )
+    }
+
+    fn request(id: Option<u64>, method: &str, params: Value) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            id: id.map(Value::from),
+            method: method.into(),
+            params: Some(params),
+        }
+    }
+
+    #[test]
+    fn negotiates_supported_version() {
+        assert_eq!(negotiate_version(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(negotiate_version(Some("2025-03-26")), "2025-03-26");
+        assert_eq!(negotiate_version(Some("1999-01-01")), PROTOCOL_VERSIONS[0]);
+        assert_eq!(negotiate_version(None), PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut bridge = bridge();
+
+        let early = bridge
+            .handle(&request(Some(1), "tools/list", json!({})))
+            .unwrap();
+        assert_eq!(early.error.unwrap().code, INVALID_REQUEST);
+        assert!(bridge
+            .handle(&request(Some(2), "ping", json!({})))
+            .unwrap()
+            .result
+            .is_some());
+
+        let init = bridge
+            .handle(&request(
+                Some(3),
+                "initialize",
+                json!({ "protocolVersion": "2025-03-26" }),
+            ))
+            .unwrap();
+        assert_eq!(init.result.unwrap()["protocolVersion"], "2025-03-26");
+        assert_eq!(bridge.lifecycle(), Lifecycle::Initializing);
+
+        assert!(bridge
+            .handle(&request(None, "notifications/initialized", json!({})))
+            .is_none());
+        assert_eq!(bridge.lifecycle(), Lifecycle::Ready);
+
+        let again = bridge
+            .handle(&request(Some(4), "initialize", json!({})))
+            .unwrap();
+        assert_eq!(again.error.unwrap().code, INVALID_REQUEST);
+        let tools = bridge
+            .handle(&request(Some(5), "tools/list", json!({})))
+            .unwrap();
+        assert_eq!(tools.result.unwrap(), json!({ "tools": [] }));
+    }
+
+    #[test]
+    fn unknown_methods_are_not_forwarded() {
+        let mut bridge = bridge();
+        bridge.handle(&request(Some(1), "initialize", json!({})));
+        let response = bridge
+            .handle(&request(Some(2), "shutdown", json!({})))
+            .unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, METHOD_NOT_FOUND);
+        assert_eq!(error.message, "Method not found: shutdown");
+    }
+}
@@ -0,0 +1,415 @@
+//! Structured tool results: an `outputSchema` per tool for `tools/list`, and
+//! `structuredContent` with a readable text rendering for `tools/call`.
+//!
+//! MCP requires structured content to be an object, so tools that return a
+//! list have it wrapped under a key, e.g. `{ "messages": [...] }`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::cli::format;
+use crate::error::check_tool_result;
+use crate::model::{
+    Account, Calendar, ComposeResult, Contact, Folder, MessageSummary, UpdateResult,
+};
+
+/// First MCP revision with `outputSchema` and `structuredContent`.
+pub const STRUCTURED_OUTPUT_VERSION: &str = "2025-06-18";
+
+/// Key that list results are wrapped under in `structuredContent`.
+fn list_key(tool: &str) -> Option<&'static str> {
+    match tool {
+        "listAccounts" => Some("accounts"),
+        "searchMessages" => Some("messages"),
+        "listFolders" => Some("folders"),
+        "searchContacts" => Some("contacts"),
+        "listCalendars" => Some("calendars"),
+        _ => None,
+    }
+}
+
+/// JSON Schema of what `tools/call` puts in `structuredContent` for `tool`.
+pub fn output_schema(tool: &str) -> Option<Value> {
+    let item = match tool {
+        "listAccounts" => account_schema(),
+        "searchMessages" => message_summary_schema(),
+        "getMessage" => return Some(message_schema()),
+        "listFolders" => folder_schema(),
+        "searchContacts" => contact_schema(),
+        "listCalendars" => calendar_schema(),
+        "updateMessage" => return Some(update_schema()),
+        "sendMail" | "replyToMessage" | "forwardMessage" | "createEvent" => {
+            return Some(compose_schema())
+        }
+        _ => return None,
+    };
+    let key = list_key(tool)?;
+    Some(object(json!({ key: array(item) }), &[key]))
+}
+
+/// Add an `outputSchema` to every tool in the extension's `listTools` answer
+/// that has one.
+pub fn add_output_schemas(list: &mut Value) {
+    let Some(tools) = list.get_mut("tools").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for tool in tools {
+        let schema = tool
+            .get("name")
+            .and_then(Value::as_str)
+            .and_then(output_schema);
+        if let Some(schema) = schema {
+            tool["outputSchema"] = schema;
+        }
+    }
+}
+
+/// Turn a tool's result into an MCP `CallToolResult`: the `cli::format`
+/// rendering as text, with the normalized result as `structuredContent` when
+/// `structured` is set. Clients without `structuredContent` get the result
+/// serialized as JSON in a second text block instead, which carries the ids
+/// a follow-up call needs. Results of tools without a model are passed on as
+/// JSON text.
+///
+/// `{ "error": ... }` envelopes become `isError` results, so clients see a
+/// failed call rather than content they might take for email. So do results
+/// that do not decode, since they would not match the declared `outputSchema`.
+pub fn tool_result(tool: &str, result: Value, structured: bool) -> Value {
+    if let Err(e) = check_tool_result(&result) {
+        return error_result(&format!("{} failed: {}", tool, e));
+    }
+    let (text, content) = match present(tool, &result) {
+        Some(Ok((text, content))) => (Some(text), content),
+        Some(Err(e)) => {
+            return error_result(&format!("{} returned an unexpected result: {}", tool, e));
+        }
+        None => (None, wrap(tool, result)),
+    };
+    // A declared outputSchema promises structuredContent on every result
+    let structured = structured && output_schema(tool).is_some();
+    let json = (!structured).then(|| serde_json::to_string(&content).unwrap_or_default());
+    let blocks: Vec<Value> = text
+        .into_iter()
+        .chain(json)
+        .map(|text| json!({ "type": "text", "text": text }))
+        .collect();
+    if structured {
+        json!({ "content": blocks, "structuredContent": content })
+    } else {
+        json!({ "content": blocks })
+    }
+}
+
+/// A `CallToolResult` reporting that the tool ran and failed.
+pub fn error_result(message: &str) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": message }],
+        "isError": true,
+    })
+}
+
+/// Decode `result` as the tool's model type, returning its text rendering and
+/// its structured content, or why it does not decode. `None` for tools
+/// without a model.
+fn present(tool: &str, result: &Value) -> Option<Result<(String, Value), String>> {
+    match tool {
+        "listAccounts" => render(tool, result, |a: &Vec<Account>| format::render_accounts(a)),
+        "searchMessages" => render(tool, result, |m: &Vec<MessageSummary>| {
+            format::render_messages(m)
+        }),
+        "getMessage" => render(tool, result, format::render_message),
+        "listFolders" => render(tool, result, |f: &Vec<Folder>| format::render_folders(f)),
+        "searchContacts" => render(tool, result, |c: &Vec<Contact>| format::render_contacts(c)),
+        "listCalendars" => render(tool, result, |c: &Vec<Calendar>| {
+            format::render_calendars(c)
+        }),
+        "updateMessage" => render(tool, result, |r: &UpdateResult| {
+            let text = format::render_update(r);
+            if text.is_empty() {
+                "No changes made.\n".into()
+            } else {
+                text
+            }
+        }),
+        "sendMail" => render(tool, result, |r| {
+            format::render_compose(r, "Compose window opened.")
+        }),
+        "replyToMessage" => render(tool, result, |r| {
+            format::render_compose(r, "Reply compose window opened.")
+        }),
+        "forwardMessage" => render(tool, result, |r| {
+            format::render_compose(r, "Forward compose window opened.")
+        }),
+        "createEvent" => render(tool, result, |r: &ComposeResult| {
+            format::render_compose(r, "Event dialog opened.")
+        }),
+        _ => None,
+    }
+}
+
+fn render<T: DeserializeOwned + Serialize>(
+    tool: &str,
+    result: &Value,
+    text: impl Fn(&T) -> String,
+) -> Option<Result<(String, Value), String>> {
+    let decoded = serde_json::from_value::<T>(result.clone()).and_then(|decoded| {
+        // Re-serialize so nulls from the extension match the schema's types
+        let normalized = serde_json::to_value(&decoded)?;
+        Ok((text(&decoded), wrap(tool, normalized)))
+    });
+    Some(decoded.map_err(|e| e.to_string()))
+}
+
+/// `result` as structured content, which MCP requires to be an object.
+fn wrap(tool: &str, result: Value) -> Value {
+    match list_key(tool) {
+        Some(key) => json!({ key: result }),
+        None if result.is_object() => result,
+        None => json!({ "result": result }),
+    }
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn string(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn boolean(description: &str) -> Value {
+    json!({ "type": "boolean", "description": description })
+}
+
+fn integer(description: &str) -> Value {
+    json!({ "type": "integer", "minimum": 0, "description": description })
+}
+
+fn account_schema() -> Value {
+    object(
+        json!({
+            "id": string("Account ID, for listFolders"),
+            "name": string("Account name"),
+            "type": string("Server type, e.g. imap or none for Local Folders"),
+            "identities": array(object(
+                json!({
+                    "id": string("Identity ID, usable as from in compose tools"),
+                    "email": string("Email address"),
+                    "name": string("Display name"),
+                    "isDefault": boolean("Default identity of the account"),
+                }),
+                &["id", "email"],
+            )),
+        }),
+        &["id", "name"],
+    )
+}
+
+fn message_summary_schema() -> Value {
+    object(
+        json!({
+            "id": string("Message ID, for getMessage"),
+            "subject": string("Subject"),
+            "author": string("Sender"),
+            "recipients": string("To recipients"),
+            "ccList": string("CC recipients"),
+            "date": string("ISO 8601 date, empty if the message has none"),
+            "folder": string("Folder display name"),
+            "folderPath": string("Folder URI, for getMessage"),
+            "read": boolean("Whether the message has been read"),
+            "flagged": boolean("Whether the message is flagged"),
+        }),
+        &["id", "folderPath"],
+    )
+}
+
+fn message_schema() -> Value {
+    object(
+        json!({
+            "id": string("Message ID"),
+            "subject": string("Subject"),
+            "author": string("Sender"),
+            "recipients": string("To recipients"),
+            "ccList": string("CC recipients"),
+            "date": string("ISO 8601 date"),
+            "read": boolean("Whether the message has been read"),
+            "flagged": boolean("Whether the message is flagged"),
+            "body": string("Body text"),
+            "bodyIsHtml": boolean("The body was extracted from an HTML part and stripped to text"),
+            "attachments": array(object(
+                json!({
+                    "name": string("File name"),
+                    "contentType": string("MIME type"),
+                    "size": integer("Size in bytes"),
+                    "filePath": string("Where the attachment was saved, with saveAttachments"),
+                    "error": string("Why the attachment could not be saved"),
+                }),
+                &["name"],
+            )),
+        }),
+        &["id"],
+    )
+}
+
+fn folder_schema() -> Value {
+    object(
+        json!({
+            "name": string("Folder name"),
+            "path": string("Folder URI, for folderPath and moveTo"),
+            "accountId": string("Account ID"),
+            "totalMessages": integer("Number of messages"),
+            "unreadMessages": integer("Number of unread messages"),
+            "depth": integer("Nesting depth, 0 for top-level folders"),
+        }),
+        &["name", "path"],
+    )
+}
+
+fn contact_schema() -> Value {
+    object(
+        json!({
+            "id": string("Contact ID"),
+            "displayName": string("Display name"),
+            "email": string("Primary email address"),
+            "firstName": string("First name"),
+            "lastName": string("Last name"),
+            "addressBook": string("Address book name"),
+        }),
+        &["email"],
+    )
+}
+
+fn calendar_schema() -> Value {
+    object(
+        json!({
+            "id": string("Calendar ID, for createEvent"),
+            "name": string("Calendar name"),
+            "type": string("Provider type, e.g. storage or caldav"),
+            "readOnly": boolean("Events cannot be created in this calendar"),
+            "color": string("Display color"),
+        }),
+        &["id", "name"],
+    )
+}
+
+fn update_schema() -> Value {
+    object(
+        json!({
+            "success": boolean("Whether the update succeeded"),
+            "actions": array(string("What was done, e.g. marked read or trashed")),
+        }),
+        &["success", "actions"],
+    )
+}
+
+fn compose_schema() -> Value {
+    object(
+        json!({
+            "success": boolean("Whether the window was opened"),
+            "message": string("What was opened, for the user to review"),
+        }),
+        &["success", "message"],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_are_wrapped_and_rendered() {
+        let result = json!([{
+            "id": "a@example.com",
+            "subject": "Hello",
+            "author": "Alice <alice@example.com>",
+            "date": "2026-02-19T14:30:00.000Z",
+            "folderPath": "mailbox://x/Inbox",
+            "recipients": null,
+            "read": true
+        }]);
+        let call = tool_result("searchMessages", result, true);
+        let text = call["content"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("19 Feb 2026 14:30  Alice <alice@example.com>\n"));
+        assert_eq!(
+            call["structuredContent"]["messages"][0]["id"],
+            "a@example.com"
+        );
+        assert_eq!(call["structuredContent"]["messages"][0]["recipients"], "");
+    }
+
+    #[test]
+    fn older_clients_get_text_and_json() {
+        let calendars = json!([{ "id": "cal-1", "name": "Home", "readOnly": false }]);
+        let call = tool_result("listCalendars", calendars, false);
+        assert!(call["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Home"));
+        let json: Value =
+            serde_json::from_str(call["content"][1]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(json["calendars"][0]["id"], "cal-1");
+        assert_eq!(json["calendars"][0]["readOnly"], false);
+        assert!(call.get("structuredContent").is_none());
+    }
+
+    #[test]
+    fn structured_results_skip_the_json_block() {
+        let calendars = json!([{ "id": "cal-1", "name": "Home", "readOnly": false }]);
+        let call = tool_result("listCalendars", calendars, true);
+        assert_eq!(call["content"].as_array().unwrap().len(), 1);
+        assert_eq!(call["structuredContent"]["calendars"][0]["id"], "cal-1");
+    }
+
+    #[test]
+    fn undecodable_results_are_tool_errors() {
+        let call = tool_result("listFolders", json!("no folders yet"), true);
+        assert_eq!(call["isError"], true);
+        assert!(call["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("listFolders returned an unexpected result: "));
+        assert!(call.get("structuredContent").is_none());
+
+        let call = tool_result("someNewTool", json!([1, 2]), true);
+        assert!(call.get("isError").is_none());
+        assert_eq!(call["content"][0]["text"], r#"{"result":[1,2]}"#);
+        assert!(call.get("structuredContent").is_none());
+    }
+
+    #[test]
+    fn error_envelope_is_tool_error() {
+        let call = tool_result(
+            "getMessage",
+            json!({ "error": "Message not found: x" }),
+            true,
+        );
+        assert_eq!(call["isError"], true);
+        assert_eq!(
+            call["content"][0]["text"],
+            "getMessage failed: Message not found: x"
+        );
+        assert!(call.get("structuredContent").is_none());
+    }
+
+    #[test]
+    fn every_schema_is_an_object() {
+        let mut list = json!({ "tools": [{ "name": "getMessage" }, { "name": "unknown" }] });
+        add_output_schemas(&mut list);
+        assert_eq!(list["tools"][0]["outputSchema"]["type"], "object");
+        assert!(list["tools"][1].get("outputSchema").is_none());
+        for tool in [
+            "listAccounts",
+            "searchMessages",
+            "listFolders",
+            "updateMessage",
+            "sendMail",
+        ] {
+            assert_eq!(output_schema(tool).unwrap()["type"], "object");
+        }
+    }
+}
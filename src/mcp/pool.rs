@@ -0,0 +1,231 @@
+//! Worker threads for requests that call the extension, so a slow
+//! `getMessage` with `saveAttachments` does not hold up `ping` or other calls.
+//!
+//! Responses are written as calls complete, in any order; clients match them
+//! to requests by id. `notifications/cancelled` marks a call in flight, and its
+//! response is dropped instead of written.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::types::JsonRpcResponse;
+
+/// Extension calls that can run at the same time.
+pub const DEFAULT_WORKERS: usize = 4;
+
+pub(crate) type Work = Box<dyn FnOnce() -> JsonRpcResponse + Send>;
+
+/// Requests handed to the pool and not answered yet, by id.
+#[derive(Clone, Default)]
+pub(crate) struct InFlight(Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>);
+
+impl InFlight {
+    fn start(&self, id: &Value) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.lock().insert(id.to_string(), Arc::clone(&cancelled));
+        cancelled
+    }
+
+    /// Whether a request with this id is still running.
+    pub(crate) fn contains(&self, id: &Value) -> bool {
+        self.lock().contains_key(&id.to_string())
+    }
+
+    pub(crate) fn is_cancelled(&self, id: &Value) -> bool {
+        self.lock()
+            .get(&id.to_string())
+            .is_some_and(|cancelled| cancelled.load(Ordering::SeqCst))
+    }
+
+    /// Cancelling a request that already finished, or never existed, does nothing.
+    pub(crate) fn cancel(&self, id: &Value) {
+        if let Some(cancelled) = self.lock().get(&id.to_string()) {
+            cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn finish(&self, id: &Value) {
+        self.lock().remove(&id.to_string());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A request the bridge answers by calling the extension. Runs on any thread.
+pub struct Job {
+    id: Value,
+    cancelled: Arc<AtomicBool>,
+    in_flight: InFlight,
+    work: Work,
+    waits_for_user: bool,
+}
+
+impl Job {
+    pub(crate) fn new(id: Value, in_flight: &InFlight, work: Work) -> Self {
+        Self {
+            cancelled: in_flight.start(&id),
+            in_flight: in_flight.clone(),
+            id,
+            work,
+            waits_for_user: false,
+        }
+    }
+
+    /// Mark a job that may wait for the user, so [`WorkerPool`] gives it a
+    /// thread of its own instead of a worker.
+    pub(crate) fn waiting_for_user(self) -> Self {
+        Self {
+            waits_for_user: true,
+            ..self
+        }
+    }
+
+    pub fn waits_for_user(&self) -> bool {
+        self.waits_for_user
+    }
+
+    /// The response, or `None` if the client cancelled the request. A request
+    /// cancelled while still queued is never sent to the extension.
+    pub fn run(self) -> Option<JsonRpcResponse> {
+        let Self {
+            id,
+            cancelled,
+            in_flight,
+            work,
+            ..
+        } = self;
+        let response = if cancelled.load(Ordering::SeqCst) {
+            None
+        } else {
+            Some(work())
+        };
+        in_flight.finish(&id);
+        response.filter(|_| !cancelled.load(Ordering::SeqCst))
+    }
+}
+
+/// A fixed number of threads running [`Job`]s. Jobs that wait for the user
+/// get a thread of their own, so pending confirmations never hold up other
+/// calls. Dropping the pool waits for all jobs, so no response is lost when
+/// stdin closes.
+pub struct WorkerPool {
+    queue: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    respond: Arc<dyn Fn(&JsonRpcResponse) + Send + Sync>,
+    /// Threads of jobs waiting for the user.
+    waiting: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl WorkerPool {
+    /// `size` threads, each passing finished responses to `respond`.
+    pub fn new(size: usize, respond: impl Fn(&JsonRpcResponse) + Send + Sync + 'static) -> Self {
+        let (queue, jobs) = mpsc::channel::<Job>();
+        let jobs = Arc::new(Mutex::new(jobs));
+        let respond: Arc<dyn Fn(&JsonRpcResponse) + Send + Sync> = Arc::new(respond);
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let jobs = Arc::clone(&jobs);
+                let respond = Arc::clone(&respond);
+                thread::spawn(move || loop {
+                    // The lock is released before the job runs
+                    let next = jobs.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                    let Ok(job) = next else { break };
+                    if let Some(response) = job.run() {
+                        respond(&response);
+                    }
+                })
+            })
+            .collect();
+        Self {
+            queue: Some(queue),
+            workers,
+            respond,
+            waiting: Mutex::default(),
+        }
+    }
+
+    pub fn submit(&self, job: Job) {
+        if job.waits_for_user() {
+            let respond = Arc::clone(&self.respond);
+            let mut waiting = self.waiting.lock().unwrap_or_else(|e| e.into_inner());
+            waiting.retain(|thread| !thread.is_finished());
+            waiting.push(thread::spawn(move || {
+                if let Some(response) = job.run() {
+                    respond(&response);
+                }
+            }));
+        } else if let Some(queue) = &self.queue {
+            // Workers only stop once the queue is closed, in drop
+            let _ = queue.send(job);
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.queue.take();
+        let waiting = self.waiting.get_mut().unwrap_or_else(|e| e.into_inner());
+        for worker in self.workers.drain(..).chain(waiting.drain(..)) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::Duration;
+
+    fn job(id: u64, in_flight: &InFlight, delay: Duration) -> Job {
+        Job::new(
+            json!(id),
+            in_flight,
+            Box::new(move || {
+                thread::sleep(delay);
+                JsonRpcResponse::success(Some(json!(id)), json!({}))
+            }),
+        )
+    }
+
+    #[test]
+    fn fast_jobs_overtake_slow_ones() {
+        let (done, finished) = mpsc::channel();
+        let done = Mutex::new(done);
+        let pool = WorkerPool::new(2, move |r: &JsonRpcResponse| {
+            done.lock().unwrap().send(r.id.clone()).unwrap();
+        });
+        let in_flight = InFlight::default();
+        pool.submit(job(1, &in_flight, Duration::from_millis(300)));
+        pool.submit(job(2, &in_flight, Duration::ZERO));
+        drop(pool);
+        let order: Vec<_> = finished.iter().collect();
+        assert_eq!(order, [Some(json!(2)), Some(json!(1))]);
+    }
+
+    #[test]
+    fn cancelled_job_has_no_response() {
+        let (done, finished) = mpsc::channel();
+        let done = Mutex::new(done);
+        let pool = WorkerPool::new(1, move |r: &JsonRpcResponse| {
+            done.lock().unwrap().send(r.id.clone()).unwrap();
+        });
+        let in_flight = InFlight::default();
+        pool.submit(job(1, &in_flight, Duration::from_millis(100)));
+        pool.submit(job(2, &in_flight, Duration::ZERO));
+        in_flight.cancel(&json!(1));
+        in_flight.cancel(&json!(2));
+        drop(pool);
+        assert_eq!(
+            finished.recv_timeout(Duration::ZERO),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        assert!(in_flight.lock().is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! Which extension tools the bridge exposes. `tools/list` hides the rest and
+//! `tools/call` refuses them, so an agent meant to read cannot trash mail.
+//!
+//! - `readonly`: tools that only read (the ones retried, see
+//!   [`IDEMPOTENT_TOOLS`](crate::retry::IDEMPOTENT_TOOLS))
+//! - `compose`: those plus the tools that open a compose window or event
+//!   dialog for the user to review; not `updateMessage`
+//! - `full`: every tool, including ones the bridge does not know
+//! - `custom`: only the tools in the allow list
+//!
+//! The allow list adds tools to any profile and the deny list removes them.
+
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Result, ThunderbirdError, INVALID_PARAMS};
+use crate::retry::is_idempotent;
+use crate::types::JsonRpcError;
+
+/// Tools that open a window for the user instead of changing mail directly.
+pub const COMPOSE_TOOLS: &[&str] = &[
+    "sendMail",
+    "replyToMessage",
+    "forwardMessage",
+    "createEvent",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profile {
+    ReadOnly,
+    Compose,
+    #[default]
+    Full,
+    Custom,
+}
+
+impl Profile {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Profile::ReadOnly => "readonly",
+            Profile::Compose => "compose",
+            Profile::Full => "full",
+            Profile::Custom => "custom",
+        }
+    }
+
+    fn includes(self, tool: &str) -> bool {
+        match self {
+            Profile::ReadOnly => is_idempotent(tool),
+            Profile::Compose => is_idempotent(tool) || COMPOSE_TOOLS.contains(&tool),
+            Profile::Full => true,
+            Profile::Custom => false,
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "readonly" | "read-only" => Ok(Profile::ReadOnly),
+            "compose" => Ok(Profile::Compose),
+            "full" => Ok(Profile::Full),
+            "custom" => Ok(Profile::Custom),
+            other => Err(format!(
+                "Unknown tool profile: {} (expected readonly, compose, full or custom)",
+                other
+            )),
+        }
+    }
+}
+
+/// A profile with its allow and deny lists. The default allows everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolPolicy {
+    profile: Profile,
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    /// `custom` needs at least one allowed tool.
+    pub fn new(profile: Profile, allow: Vec<String>, deny: Vec<String>) -> Result<Self> {
+        if profile == Profile::Custom && allow.is_empty() {
+            return Err(ThunderbirdError::Config(
+                "Tool profile custom needs an allow list".into(),
+            ));
+        }
+        Ok(Self {
+            profile,
+            allow,
+            deny,
+        })
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    pub fn allows(&self, tool: &str) -> bool {
+        let listed = |list: &[String]| list.iter().any(|t| t == tool);
+        !listed(&self.deny) && (listed(&self.allow) || self.profile.includes(tool))
+    }
+
+    /// The error for a `tools/call` outside the policy.
+    pub fn check(&self, tool: &str) -> std::result::Result<(), JsonRpcError> {
+        if self.allows(tool) {
+            return Ok(());
+        }
+        Err(JsonRpcError {
+            code: INVALID_PARAMS,
+            message: format!(
+                "Tool {} is not allowed by the bridge's {} tool profile",
+                tool, self.profile
+            ),
+        })
+    }
+
+    /// Drop the tools outside the policy from a `listTools` result.
+    pub fn filter_list(&self, result: &mut Value) {
+        if let Some(tools) = result.get_mut("tools").and_then(Value::as_array_mut) {
+            tools.retain(|t| self.allows(t.get("name").and_then(Value::as_str).unwrap_or("")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn profiles() {
+        let readonly = ToolPolicy::new(Profile::ReadOnly, vec![], vec![]).unwrap();
+        assert!(readonly.allows("searchMessages"));
+        assert!(!readonly.allows("sendMail"));
+        assert!(!readonly.allows("somethingNew"));
+
+        let compose = ToolPolicy::new(Profile::Compose, vec![], vec![]).unwrap();
+        assert!(compose.allows("replyToMessage"));
+        assert!(!compose.allows("updateMessage"));
+
+        assert!(ToolPolicy::default().allows("somethingNew"));
+    }
+
+    #[test]
+    fn allow_and_deny_lists() {
+        let policy = ToolPolicy::new(
+            Profile::ReadOnly,
+            names(&["createEvent"]),
+            names(&["getMessage"]),
+        )
+        .unwrap();
+        assert!(policy.allows("createEvent"));
+        assert!(!policy.allows("getMessage"));
+
+        let custom = ToolPolicy::new(Profile::Custom, names(&["listFolders"]), vec![]).unwrap();
+        assert!(custom.allows("listFolders"));
+        assert!(!custom.allows("listAccounts"));
+        assert!(ToolPolicy::new(Profile::Custom, vec![], vec![]).is_err());
+    }
+
+    #[test]
+    fn list_is_filtered() {
+        let policy = ToolPolicy::new(Profile::ReadOnly, vec![], vec![]).unwrap();
+        let mut result =
+            json!({ "tools": [{ "name": "listFolders" }, { "name": "updateMessage" }] });
+        policy.filter_list(&mut result);
+        assert_eq!(result, json!({ "tools": [{ "name": "listFolders" }] }));
+
+        let err = policy.check("updateMessage").unwrap_err();
+        assert_eq!(err.code, INVALID_PARAMS);
+        assert_eq!(
+            err.message,
+            "Tool updateMessage is not allowed by the bridge's readonly tool profile"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! `notifications/progress` for `tools/call` requests that carry a
+//! `progressToken`.
+//!
+//! While the call runs, a notification is sent every [`PROGRESS_INTERVAL`].
+//! The bridge passes a token of its own to the extension in
+//! `_meta.progressToken`; where the extension tracks progress (folders
+//! searched, attachments saved) it answers `getProgress` with counts that are
+//! relayed, and otherwise the notifications are heartbeats.
+//!
+//! MCP requires `progress` to increase with every notification, so an
+//! interval without news counts as one step: `progress` is the intervals
+//! without news plus the items done, out of the same intervals plus the total.
+
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use super::Outbox;
+use crate::client::ThunderbirdClient;
+use crate::model::Progress;
+
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// A token for the extension that no other bridge process uses, since one
+/// Thunderbird may serve several bridges.
+pub(crate) fn extension_token() -> String {
+    format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT_TOKEN.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+/// The client's `params._meta.progressToken`, a string or an integer.
+pub(crate) fn request_token(params: Option<&Value>) -> Option<Value> {
+    params?
+        .get("_meta")?
+        .get("progressToken")
+        .filter(|t| t.is_string() || t.is_i64() || t.is_u64())
+        .cloned()
+}
+
+/// Turns samples from the extension into increasing `progress` values.
+#[derive(Debug, Default)]
+struct Tracker {
+    idle: u64,
+    done: u64,
+}
+
+impl Tracker {
+    fn next(&mut self, report: Option<&Progress>) -> Value {
+        match report {
+            Some(r) if r.progress > self.done => self.done = r.progress,
+            _ => self.idle += 1,
+        }
+        let mut params = json!({ "progress": self.idle + self.done });
+        if let Some(total) = report.and_then(|r| r.total) {
+            params["total"] = json!(self.idle + total.max(self.done));
+        }
+        params["message"] = json!(report
+            .and_then(|r| r.message.clone())
+            .unwrap_or_else(|| "Waiting for Thunderbird".into()));
+        params
+    }
+}
+
+/// Run `call`, sending progress for `token` until it returns. The last
+/// notification is sent before this returns, so it always precedes the
+/// response.
+pub(crate) fn with_progress<T>(
+    client: &ThunderbirdClient,
+    outbox: &Outbox,
+    token: Value,
+    extension_token: &str,
+    call: impl FnOnce() -> T,
+) -> T {
+    let (done, finished) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut tracker = Tracker::default();
+            while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(PROGRESS_INTERVAL) {
+                // Older extensions have no getProgress; that is just a heartbeat
+                let report = client.get_progress(extension_token).ok().flatten();
+                let mut params = tracker.next(report.as_ref());
+                params["progressToken"] = token.clone();
+                outbox.notify("notifications/progress", params);
+            }
+        });
+        let result = call();
+        drop(done);
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(progress: u64, total: u64) -> Option<Progress> {
+        Some(Progress {
+            progress,
+            total: Some(total),
+            message: Some(format!("Searched {} of {} folders", progress, total)),
+        })
+    }
+
+    #[test]
+    fn progress_always_increases() {
+        let mut tracker = Tracker::default();
+        let samples = [
+            None,
+            report(0, 10),
+            report(4, 10),
+            report(4, 10),
+            report(10, 10),
+        ];
+        let sent: Vec<_> = samples.iter().map(|s| tracker.next(s.as_ref())).collect();
+
+        assert_eq!(sent[0]["progress"], 1);
+        assert_eq!(sent[0]["message"], "Waiting for Thunderbird");
+        let progress: Vec<_> = sent
+            .iter()
+            .map(|p| p["progress"].as_u64().unwrap())
+            .collect();
+        assert_eq!(progress, [1, 2, 6, 7, 13]);
+        assert_eq!(sent[4]["total"], 13);
+        assert_eq!(sent[2]["message"], "Searched 4 of 10 folders");
+    }
+
+    #[test]
+    fn token_must_be_string_or_integer() {
+        let params = |token: Value| json!({ "_meta": { "progressToken": token } });
+        assert_eq!(request_token(Some(&params(json!("a")))), Some(json!("a")));
+        assert_eq!(request_token(Some(&params(json!(7)))), Some(json!(7)));
+        assert_eq!(request_token(Some(&params(json!(1.5)))), None);
+        assert_eq!(request_token(Some(&json!({}))), None);
+        assert_eq!(request_token(None), None);
+    }
+}
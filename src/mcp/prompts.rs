@@ -0,0 +1,337 @@
+//! `prompts/list` and `prompts/get`: built-in prompts for common mail
+//! workflows, plus the user's own from `prompts/*.toml` in the config directory.
+//!
+//! A prompt file is named after the prompt and holds its text with
+//! `{{argument}}` placeholders:
+//!
+//! ```toml
+//! # ~/.config/thunderbird-api/prompts/weekly-report.toml
+//! title = "Weekly report"
+//! description = "Summarize what I sent this week"
+//! template = "Use searchMessages to find mail I sent since {{since}} ..."
+//!
+//! [[arguments]]
+//! name = "since"
+//! description = "Start date"
+//! default = "last Monday"
+//! ```
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Result, ThunderbirdError, INVALID_PARAMS};
+use crate::types::JsonRpcError;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+    /// Used when an optional argument is not given.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Prompt {
+    /// From the file name for user prompts.
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+    pub template: String,
+}
+
+impl Prompt {
+    /// Fill in `{{argument}}` placeholders. Unknown arguments are ignored.
+    pub fn render(&self, args: &Map<String, Value>) -> std::result::Result<String, JsonRpcError> {
+        let mut text = self.template.clone();
+        for arg in &self.arguments {
+            let given = args
+                .get(&arg.name)
+                .and_then(Value::as_str)
+                .filter(|v| !v.trim().is_empty());
+            let value = match (given, &arg.default) {
+                (Some(v), _) => v,
+                (None, Some(default)) => default.as_str(),
+                (None, None) if arg.required => {
+                    return Err(JsonRpcError {
+                        code: INVALID_PARAMS,
+                        message: format!("Missing argument {} for prompt {}", arg.name, self.name),
+                    })
+                }
+                (None, None) => "",
+            };
+            text = text.replace(&format!("{{{{{}}}}}", arg.name), value);
+        }
+        Ok(text)
+    }
+
+    fn to_listing(&self) -> Value {
+        let arguments: Vec<_> = self
+            .arguments
+            .iter()
+            .map(|a| {
+                json!({
+                    "name": a.name,
+                    "description": a.description,
+                    "required": a.required,
+                })
+            })
+            .collect();
+        json!({
+            "name": self.name,
+            "title": self.title,
+            "description": self.description,
+            "arguments": arguments,
+        })
+    }
+}
+
+/// Built-in prompts, then user prompts; a user prompt replaces a built-in
+/// one with the same name.
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    prompts: Vec<Prompt>,
+}
+
+impl Default for PromptLibrary {
+    fn default() -> Self {
+        Self { prompts: builtin() }
+    }
+}
+
+impl PromptLibrary {
+    /// Built-ins plus every `*.toml` in `dir`. A missing directory is fine;
+    /// an unreadable or invalid file is a configuration error.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut library = Self::default();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(library),
+            Err(e) => return Err(prompt_error(dir, e)),
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let data = fs::read_to_string(&path).map_err(|e| prompt_error(&path, e))?;
+            let mut prompt: Prompt = toml::from_str(&data).map_err(|e| prompt_error(&path, e))?;
+            prompt.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            library.add(prompt);
+        }
+        Ok(library)
+    }
+
+    pub fn add(&mut self, prompt: Prompt) {
+        self.prompts.retain(|p| p.name != prompt.name);
+        self.prompts.push(prompt);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prompt> {
+        self.prompts.iter().find(|p| p.name == name)
+    }
+
+    pub fn list(&self) -> Value {
+        let prompts: Vec<_> = self.prompts.iter().map(Prompt::to_listing).collect();
+        json!({ "prompts": prompts })
+    }
+
+    /// Answer `prompts/get` with the rendered prompt as one user message.
+    pub fn render(
+        &self,
+        name: &str,
+        args: &Map<String, Value>,
+    ) -> std::result::Result<Value, JsonRpcError> {
+        let prompt = self.get(name).ok_or_else(|| JsonRpcError {
+            code: INVALID_PARAMS,
+            message: format!("Unknown prompt: {}", name),
+        })?;
+        Ok(json!({
+            "description": prompt.description,
+            "messages": [{
+                "role": "user",
+                "content": { "type": "text", "text": prompt.render(args)? }
+            }]
+        }))
+    }
+}
+
+fn prompt_error(path: &Path, e: impl std::fmt::Display) -> ThunderbirdError {
+    ThunderbirdError::Config(format!("Invalid prompt {}: {}", path.display(), e))
+}
+
+fn arg(name: &str, description: &str, required: bool, default: Option<&str>) -> PromptArgument {
+    PromptArgument {
+        name: name.into(),
+        description: description.into(),
+        required,
+        default: default.map(Into::into),
+    }
+}
+
+fn builtin() -> Vec<Prompt> {
+    vec![
+        Prompt {
+            name: "triage-inbox".into(),
+            title: "Triage unread mail".into(),
+            description: "Sort recent unread messages by what they need from you".into(),
+            arguments: vec![arg(
+                "maxResults",
+                "How many recent messages to look at",
+                false,
+                Some("50"),
+            )],
+            template: "Triage my unread mail. Use searchMessages with an empty query and \
+                maxResults {{maxResults}}, and keep the messages that are not read. Open the ones \
+                whose subject or sender suggests they matter with getMessage.\n\n\
+                Group them into: needs a reply from me, needs some other action, FYI, and can \
+                be archived. For each message give the sender, the subject and one line on why \
+                it is in that group. Do not change anything; ask me before using updateMessage."
+                .into(),
+        },
+        Prompt {
+            name: "draft-reply".into(),
+            title: "Draft a reply".into(),
+            description: "Read a message and open a reply draft for review".into(),
+            arguments: vec![
+                arg("messageId", "Message ID, from searchMessages", true, None),
+                arg("folderPath", "Folder URI, from searchMessages", true, None),
+                arg(
+                    "tone",
+                    "Tone of the reply, e.g. formal or warm",
+                    false,
+                    Some("friendly and concise"),
+                ),
+                arg(
+                    "points",
+                    "What the reply must say",
+                    false,
+                    Some("whatever the message asks of me"),
+                ),
+            ],
+            template: "Read message {{messageId}} in folder {{folderPath}} with getMessage. \
+                Draft a reply in a {{tone}} tone that covers: {{points}}.\n\n\
+                Show me the draft, then open it with replyToMessage so I can review it in \
+                Thunderbird. Nothing is sent until I press Send."
+                .into(),
+        },
+        Prompt {
+            name: "summarize-thread".into(),
+            title: "Summarize a thread".into(),
+            description: "Find every message about a topic and summarize the conversation".into(),
+            arguments: vec![arg(
+                "topic",
+                "Subject words or a participant to search for",
+                true,
+                None,
+            )],
+            template: "Use searchMessages with the query \"{{topic}}\" and sortOrder asc to \
+                find the conversation, then read each message with getMessage in order.\n\n\
+                Summarize the thread: who is involved, what was decided, which questions are \
+                still open, and what, if anything, is expected from me."
+                .into(),
+        },
+        Prompt {
+            name: "meeting-requests".into(),
+            title: "Find meeting requests".into(),
+            description: "List messages where people asked to meet, and offer calendar events"
+                .into(),
+            arguments: vec![arg(
+                "since",
+                "How far back to look",
+                false,
+                Some("the last 7 days"),
+            )],
+            template: "Find messages from {{since}} in which someone asks to meet me or \
+                proposes a call. Use searchMessages with a startDate covering that period and \
+                read candidates with getMessage.\n\n\
+                List each request with who asked, the proposed time and the topic. Offer to \
+                open calendar events for them with createEvent (use listCalendars to pick a \
+                calendar), but do not open any until I confirm."
+                .into(),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn renders_with_defaults() {
+        let library = PromptLibrary::default();
+        let result = library
+            .render(
+                "draft-reply",
+                &args(json!({ "messageId": "a@example.com", "folderPath": "imap://x/INBOX" })),
+            )
+            .unwrap();
+        let text = result["messages"][0]["content"]["text"].as_str().unwrap();
+        assert!(text.starts_with("Read message a@example.com in folder imap://x/INBOX"));
+        assert!(text.contains("in a friendly and concise tone"));
+        assert!(!text.contains("{{"));
+    }
+
+    #[test]
+    fn missing_required_argument() {
+        let err = PromptLibrary::default()
+            .render("summarize-thread", &Map::new())
+            .unwrap_err();
+        assert_eq!(err.code, INVALID_PARAMS);
+        assert_eq!(
+            err.message,
+            "Missing argument topic for prompt summarize-thread"
+        );
+    }
+
+    #[test]
+    fn every_placeholder_is_an_argument() {
+        for prompt in builtin() {
+            let all: Map<String, Value> = prompt
+                .arguments
+                .iter()
+                .map(|a| (a.name.clone(), json!("x")))
+                .collect();
+            let text = prompt.render(&all).unwrap();
+            assert!(!text.contains("{{"), "{}", prompt.name);
+        }
+    }
+
+    #[test]
+    fn user_prompts_replace_builtins() {
+        let dir = std::env::temp_dir().join(format!("tb-prompts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("triage-inbox.toml"),
+            "title = \"Mine\"\ntemplate = \"Just the flagged ones\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        let library = PromptLibrary::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        let list = library.list();
+        let prompts = list["prompts"].as_array().unwrap();
+        assert_eq!(prompts.len(), 4);
+        assert_eq!(library.get("triage-inbox").unwrap().title, "Mine");
+    }
+}
@@ -0,0 +1,271 @@
+//! Accounts, folders and messages as MCP resources.
+//!
+//! URIs use the `thunderbird` scheme. Folder paths are themselves URIs and
+//! message IDs contain `@` and sometimes `/`, so each component is
+//! percent-encoded:
+//!
+//! - `thunderbird://account/{accountId}`
+//! - `thunderbird://folder/{folderPath}`
+//! - `thunderbird://message/{folderPath}/{messageId}` (template only)
+//!
+//! Reading a resource calls the same extension tools as `tools/call`, so the
+//! bridge's tool profile applies: a message needs `getMessage`, an account
+//! `listAccounts` and `listFolders`, a folder `listFolders`.
+
+use serde_json::{json, Value};
+
+use super::profile::ToolPolicy;
+use crate::cli::format;
+use crate::client::ThunderbirdClient;
+use crate::error::{ThunderbirdError, ToolErrorCode};
+use crate::model::{GetMessageArgs, ListFoldersArgs};
+use crate::types::JsonRpcError;
+
+/// MCP's error code for a URI that names no resource.
+pub const RESOURCE_NOT_FOUND: i32 = -32002;
+
+pub const MESSAGE_TEMPLATE: &str = "thunderbird://message/{folderPath}/{messageId}";
+
+/// A parsed `thunderbird://` resource URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceUri {
+    Account(String),
+    Folder(String),
+    Message {
+        folder_path: String,
+        message_id: String,
+    },
+}
+
+impl ResourceUri {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix("thunderbird://")?;
+        let (kind, rest) = rest.split_once('/')?;
+        match (kind, rest.split('/').collect::<Vec<_>>().as_slice()) {
+            ("account", [id]) => Some(Self::Account(decode(id)?)),
+            ("folder", [path]) => Some(Self::Folder(decode(path)?)),
+            ("message", [path, id]) => Some(Self::Message {
+                folder_path: decode(path)?,
+                message_id: decode(id)?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_uri(&self) -> String {
+        match self {
+            Self::Account(id) => format!("thunderbird://account/{}", encode(id)),
+            Self::Folder(path) => format!("thunderbird://folder/{}", encode(path)),
+            Self::Message {
+                folder_path,
+                message_id,
+            } => format!(
+                "thunderbird://message/{}/{}",
+                encode(folder_path),
+                encode(message_id)
+            ),
+        }
+    }
+}
+
+/// Every account and folder `tools` lets the bridge list. Messages are only
+/// reachable through the template.
+pub fn list(client: &ThunderbirdClient, tools: &ToolPolicy) -> Result<Value, JsonRpcError> {
+    let accounts = match tools.allows("listAccounts") {
+        true => client.list_accounts().map_err(rpc_error)?,
+        false => Vec::new(),
+    };
+    let folders = match tools.allows("listFolders") {
+        true => client
+            .list_folders(&ListFoldersArgs::default())
+            .map_err(rpc_error)?,
+        false => Vec::new(),
+    };
+
+    let mut resources = Vec::new();
+    for account in &accounts {
+        resources.push(json!({
+            "uri": ResourceUri::Account(account.id.clone()).to_uri(),
+            "name": account.name,
+            "description": format!("Email account ({}) and its folders", account.account_type),
+            "mimeType": "application/json",
+        }));
+    }
+    for folder in &folders {
+        let account = accounts
+            .iter()
+            .find(|a| a.id == folder.account_id)
+            .map_or(folder.account_id.as_str(), |a| a.name.as_str());
+        resources.push(json!({
+            "uri": ResourceUri::Folder(folder.path.clone()).to_uri(),
+            "name": folder.name,
+            "description": format!(
+                "Folder in {}: {} messages, {} unread",
+                account, folder.total_messages, folder.unread_messages
+            ),
+            "mimeType": "application/json",
+        }));
+    }
+    Ok(json!({ "resources": resources }))
+}
+
+pub fn templates() -> Value {
+    json!({
+        "resourceTemplates": [{
+            "uriTemplate": MESSAGE_TEMPLATE,
+            "name": "message",
+            "title": "Email message",
+            "description": "Headers, attachment list and body of one message. \
+                folderPath and messageId are percent-encoded, as returned by searchMessages.",
+            "mimeType": "text/plain",
+        }]
+    })
+}
+
+pub fn read(
+    client: &ThunderbirdClient,
+    tools: &ToolPolicy,
+    uri: &str,
+) -> Result<Value, JsonRpcError> {
+    let not_found = || JsonRpcError {
+        code: RESOURCE_NOT_FOUND,
+        message: format!("Resource not found: {}", uri),
+    };
+    let allowed = |tool: &str| {
+        tools.check(tool).map_err(|e| JsonRpcError {
+            code: e.code,
+            message: format!("Cannot read {}: {}", uri, e.message),
+        })
+    };
+    let contents = match ResourceUri::parse(uri).ok_or_else(not_found)? {
+        ResourceUri::Account(id) => {
+            allowed("listAccounts")?;
+            allowed("listFolders")?;
+            let account = client
+                .list_accounts()
+                .map_err(rpc_error)?
+                .into_iter()
+                .find(|a| a.id == id)
+                .ok_or_else(not_found)?;
+            let folders = client
+                .list_folders(&ListFoldersArgs {
+                    account_id: Some(id),
+                })
+                .map_err(rpc_error)?;
+            let mut value = json!(account);
+            value["folders"] = json!(folders);
+            json_contents(uri, &value)
+        }
+        ResourceUri::Folder(path) => {
+            allowed("listFolders")?;
+            let folder = client
+                .list_folders(&ListFoldersArgs::default())
+                .map_err(rpc_error)?
+                .into_iter()
+                .find(|f| f.path == path)
+                .ok_or_else(not_found)?;
+            json_contents(uri, &json!(folder))
+        }
+        ResourceUri::Message {
+            folder_path,
+            message_id,
+        } => {
+            allowed("getMessage")?;
+            let message = client
+                .get_message(&GetMessageArgs {
+                    message_id,
+                    folder_path,
+                    save_attachments: false,
+                })
+                .map_err(rpc_error)?;
+            json!({
+                "uri": uri,
+                "mimeType": "text/plain",
+                "text": format::render_message(&message),
+            })
+        }
+    };
+    Ok(json!({ "contents": [contents] }))
+}
+
+fn json_contents(uri: &str, value: &Value) -> Value {
+    json!({
+        "uri": uri,
+        "mimeType": "application/json",
+        "text": serde_json::to_string_pretty(value).unwrap_or_default(),
+    })
+}
+
+/// Missing messages and folders are `RESOURCE_NOT_FOUND`; anything else keeps
+/// the code it would have as a tool call.
+pub(crate) fn rpc_error(e: ThunderbirdError) -> JsonRpcError {
+    let code = match &e {
+        ThunderbirdError::Tool {
+            code: ToolErrorCode::NotFound,
+            ..
+        } => RESOURCE_NOT_FOUND,
+        e => e.rpc_code(),
+    };
+    JsonRpcError {
+        code,
+        message: e.to_string(),
+    }
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+pub(crate) fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+pub(crate) fn decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok().filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_uri_round_trips() {
+        let uri = ResourceUri::Message {
+            folder_path: "imap://alice%40example.com@imap.example.com/INBOX".into(),
+            message_id: "a/b@example.com".into(),
+        };
+        let text = uri.to_uri();
+        assert_eq!(
+            text,
+            "thunderbird://message/imap%3A%2F%2Falice%2540example.com%40imap.example.com%2FINBOX/a%2Fb%40example.com"
+        );
+        assert_eq!(ResourceUri::parse(&text), Some(uri));
+    }
+
+    #[test]
+    fn rejects_malformed_uris() {
+        assert_eq!(ResourceUri::parse("file:///etc/passwd"), None);
+        assert_eq!(ResourceUri::parse("thunderbird://folder/"), None);
+        assert_eq!(ResourceUri::parse("thunderbird://folder/a/b"), None);
+        assert_eq!(ResourceUri::parse("thunderbird://account/%zz"), None);
+        assert_eq!(ResourceUri::parse("thunderbird://message/only-one"), None);
+    }
+}
@@ -0,0 +1,328 @@
+//! `resources/subscribe` for folders and accounts.
+//!
+//! The extension has no push channel, so a background thread polls
+//! `listFolders` and sends `notifications/resources/updated` when the total or
+//! unread count of a subscribed folder changes. Account subscriptions fire
+//! when the sum over the account's folders changes.
+//!
+//! The bridge records a subscription as soon as it reads the request, in
+//! order with `resources/unsubscribe`, and takes the first counts on a worker.
+//! An unsubscribe that overtakes that worker still removes the subscription.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::resources::{self, ResourceUri, RESOURCE_NOT_FOUND};
+use super::Outbox;
+use crate::client::ThunderbirdClient;
+use crate::error::INVALID_PARAMS;
+use crate::model::{Folder, ListFoldersArgs};
+use crate::types::JsonRpcError;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Counts {
+    total: u64,
+    unread: u64,
+}
+
+/// One subscription. `counts` is `None` until the first `listFolders`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watch {
+    /// Tells a subscription apart from a later one to the same URI.
+    serial: u64,
+    counts: Option<Counts>,
+}
+
+type Watched = Arc<Mutex<HashMap<String, Watch>>>;
+
+/// A recorded subscription still waiting for its first counts.
+#[derive(Debug)]
+pub struct Registration {
+    uri: String,
+    resource: ResourceUri,
+    serial: u64,
+}
+
+/// Subscribed resource URIs and the poller watching them. The poller starts
+/// with the first subscription and stops when this is dropped.
+pub struct Subscriptions {
+    interval: Duration,
+    watched: Watched,
+    next_serial: AtomicU64,
+    /// Dropping the sender wakes the poller and ends it.
+    stop: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Subscriptions {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            watched: Arc::default(),
+            next_serial: AtomicU64::new(0),
+            stop: Mutex::default(),
+        }
+    }
+
+    /// Record a subscription and take its first counts.
+    pub fn subscribe(
+        &self,
+        client: &Arc<ThunderbirdClient>,
+        outbox: &Outbox,
+        uri: &str,
+    ) -> Result<Value, JsonRpcError> {
+        let registration = self.register(client, outbox, uri)?;
+        self.baseline(client, registration)
+    }
+
+    /// Record a subscription to `uri`, without calling the extension, and
+    /// start the poller. Call [`Subscriptions::baseline`] next.
+    pub fn register(
+        &self,
+        client: &Arc<ThunderbirdClient>,
+        outbox: &Outbox,
+        uri: &str,
+    ) -> Result<Registration, JsonRpcError> {
+        let resource = match ResourceUri::parse(uri) {
+            Some(r @ (ResourceUri::Folder(_) | ResourceUri::Account(_))) => r,
+            Some(ResourceUri::Message { .. }) => {
+                return Err(JsonRpcError {
+                    code: INVALID_PARAMS,
+                    message: "Only folder and account resources can be subscribed".into(),
+                })
+            }
+            None => return Err(not_found(uri)),
+        };
+        let serial = self.next_serial.fetch_add(1, Ordering::Relaxed);
+        let watch = Watch {
+            serial,
+            counts: None,
+        };
+        lock(&self.watched).insert(uri.to_string(), watch);
+
+        let mut stop = self.stop.lock().unwrap_or_else(|e| e.into_inner());
+        if stop.is_none() {
+            *stop = Some(self.start(Arc::clone(client), outbox.clone()));
+        }
+        Ok(Registration {
+            uri: uri.to_string(),
+            resource,
+            serial,
+        })
+    }
+
+    /// Take the first counts for a registered subscription, unless it was
+    /// unsubscribed meanwhile. A resource that does not exist is dropped.
+    pub fn baseline(
+        &self,
+        client: &ThunderbirdClient,
+        registration: Registration,
+    ) -> Result<Value, JsonRpcError> {
+        let Registration {
+            uri,
+            resource,
+            serial,
+        } = registration;
+        let counts = client
+            .list_folders(&ListFoldersArgs::default())
+            .map_err(resources::rpc_error)
+            .and_then(|folders| counts(&folders, &resource).ok_or_else(|| not_found(&uri)));
+
+        let mut watched = lock(&self.watched);
+        let current = watched.get_mut(&uri).filter(|w| w.serial == serial);
+        match (counts, current) {
+            (Ok(counts), Some(watch)) => {
+                watch.counts.get_or_insert(counts);
+            }
+            (Err(e), Some(_)) => {
+                watched.remove(&uri);
+                return Err(e);
+            }
+            (Err(e), None) => return Err(e),
+            (Ok(_), None) => {}
+        }
+        Ok(json!({}))
+    }
+
+    /// Unsubscribing from something that was not subscribed is not an error.
+    pub fn unsubscribe(&self, uri: &str) -> Result<Value, JsonRpcError> {
+        lock(&self.watched).remove(uri);
+        Ok(json!({}))
+    }
+
+    #[cfg(test)]
+    fn is_subscribed(&self, uri: &str) -> bool {
+        lock(&self.watched).contains_key(uri)
+    }
+
+    fn start(&self, client: Arc<ThunderbirdClient>, outbox: Outbox) -> mpsc::Sender<()> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let watched = Arc::clone(&self.watched);
+        let interval = self.interval;
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                poll(&client, &watched, &outbox);
+            }
+        });
+        stop
+    }
+}
+
+/// Compare current counts with the last ones seen and notify about changes.
+/// Failures, e.g. while Thunderbird restarts, are skipped until the next poll.
+fn poll(client: &ThunderbirdClient, watched: &Watched, outbox: &Outbox) {
+    if lock(watched).is_empty() {
+        return;
+    }
+    let Ok(folders) = client.list_folders(&ListFoldersArgs::default()) else {
+        return;
+    };
+
+    let mut changed = Vec::new();
+    for (uri, watch) in lock(watched).iter_mut() {
+        let current = ResourceUri::parse(uri).and_then(|r| counts(&folders, &r));
+        match (current, &mut watch.counts) {
+            (Some(current), Some(last)) if current != *last => {
+                *last = current;
+                changed.push(uri.clone());
+            }
+            // The subscribe call was cancelled before it took a baseline
+            (Some(current), counts @ None) => *counts = Some(current),
+            _ => {}
+        }
+    }
+    for uri in changed {
+        outbox.notify("notifications/resources/updated", json!({ "uri": uri }));
+    }
+}
+
+fn counts(folders: &[Folder], resource: &ResourceUri) -> Option<Counts> {
+    let sum = |matching: Vec<&Folder>| Counts {
+        total: matching.iter().map(|f| f.total_messages).sum(),
+        unread: matching.iter().map(|f| f.unread_messages).sum(),
+    };
+    match resource {
+        ResourceUri::Folder(path) => folders
+            .iter()
+            .find(|f| &f.path == path)
+            .map(|f| sum(vec![f])),
+        ResourceUri::Account(id) => {
+            let matching: Vec<_> = folders.iter().filter(|f| &f.account_id == id).collect();
+            (!matching.is_empty()).then(|| sum(matching))
+        }
+        ResourceUri::Message { .. } => None,
+    }
+}
+
+fn not_found(uri: &str) -> JsonRpcError {
+    JsonRpcError {
+        code: RESOURCE_NOT_FOUND,
+        message: format!("Resource not found: {}", uri),
+    }
+}
+
+fn lock(watched: &Watched) -> std::sync::MutexGuard<'_, HashMap<String, Watch>> {
+    watched.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::transport::MemoryTransport;
+    use crate::types::{JsonRpcRequest, JsonRpcResponse};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Instant;
+
+    const INBOX: &str = "mailbox://nobody@Local%20Folders/Inbox";
+
+    #[test]
+    fn notifies_when_unread_count_changes() {
+        let unread = Arc::new(AtomicU64::new(1));
+        let counter = Arc::clone(&unread);
+        let client = Arc::new(ThunderbirdClient::with_transport(
+            MemoryTransport::new(move |req| {
+                let folder = json!({
+                    "name": "Inbox",
+                    "path": INBOX,
+                    "accountId": "account1",
+                    "totalMessages": 3,
+                    "unreadMessages": counter.load(Ordering::SeqCst),
+                });
+                JsonRpcResponse::success(req.id, json!([folder]))
+            }),
+            RetryPolicy::none(),
+        ));
+        let sent = Arc::new(Mutex::new(Vec::<JsonRpcRequest>::new()));
+        let sink = Arc::clone(&sent);
+        let outbox = Outbox::new(move |m| sink.lock().unwrap().push(m.clone()));
+
+        let subscriptions = Subscriptions::new(Duration::from_millis(10));
+        let folder = ResourceUri::Folder(INBOX.into()).to_uri();
+        let account = ResourceUri::Account("account1".into()).to_uri();
+        subscriptions.subscribe(&client, &outbox, &folder).unwrap();
+        subscriptions.subscribe(&client, &outbox, &account).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(sent.lock().unwrap().is_empty());
+
+        unread.store(2, Ordering::SeqCst);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sent.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut uris: Vec<_> = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|n| {
+                assert_eq!(n.method, "notifications/resources/updated");
+                n.params.as_ref().unwrap()["uri"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        uris.sort();
+        assert_eq!(uris, [account, folder]);
+    }
+
+    #[test]
+    fn unknown_folder_cannot_be_subscribed() {
+        let client = Arc::new(ThunderbirdClient::with_transport(
+            MemoryTransport::new(|req| JsonRpcResponse::success(req.id, json!([]))),
+            RetryPolicy::none(),
+        ));
+        let outbox = Outbox::new(|_| {});
+        let subscriptions = Subscriptions::new(DEFAULT_POLL_INTERVAL);
+        let err = subscriptions
+            .subscribe(&client, &outbox, &ResourceUri::Folder("x".into()).to_uri())
+            .unwrap_err();
+        assert_eq!(err.code, RESOURCE_NOT_FOUND);
+    }
+
+    #[test]
+    fn unsubscribe_before_baseline_sticks() {
+        let client = Arc::new(ThunderbirdClient::with_transport(
+            MemoryTransport::new(|req| {
+                let folder = json!({ "name": "Inbox", "path": INBOX, "accountId": "account1" });
+                JsonRpcResponse::success(req.id, json!([folder]))
+            }),
+            RetryPolicy::none(),
+        ));
+        let outbox = Outbox::new(|_| {});
+        let subscriptions = Subscriptions::new(DEFAULT_POLL_INTERVAL);
+        let uri = ResourceUri::Folder(INBOX.into()).to_uri();
+
+        let registration = subscriptions.register(&client, &outbox, &uri).unwrap();
+        assert!(subscriptions.is_subscribed(&uri));
+        subscriptions.unsubscribe(&uri).unwrap();
+        subscriptions.baseline(&client, registration).unwrap();
+        assert!(!subscriptions.is_subscribed(&uri));
+    }
+}
@@ -0,0 +1,450 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The extension sends `null` for some missing header fields; treat it like absent.
+fn null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+// --- Results ---
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    #[serde(default, deserialize_with = "null_default")]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(rename = "type", default, deserialize_with = "null_default")]
+    pub account_type: String,
+    #[serde(default)]
+    pub identities: Vec<Identity>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+    #[serde(default, deserialize_with = "null_default")]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub email: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    /// Folder URI, used as `folderPath` by the other tools.
+    #[serde(default, deserialize_with = "null_default")]
+    pub path: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub account_id: String,
+    #[serde(default)]
+    pub total_messages: u64,
+    #[serde(default)]
+    pub unread_messages: u64,
+    #[serde(default)]
+    pub depth: usize,
+}
+
+/// A search hit. Use `id` and `folder_path` with `getMessage`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSummary {
+    #[serde(default, deserialize_with = "null_default")]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub subject: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub author: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub recipients: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub cc_list: String,
+    /// ISO 8601, empty if the message has no date.
+    #[serde(default, deserialize_with = "null_default")]
+    pub date: String,
+    /// Folder display name.
+    #[serde(default, deserialize_with = "null_default")]
+    pub folder: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub folder_path: String,
+    #[serde(default = "default_true")]
+    pub read: bool,
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    #[serde(default, deserialize_with = "null_default")]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub subject: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub author: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub recipients: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub cc_list: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub date: String,
+    #[serde(default = "default_true")]
+    pub read: bool,
+    #[serde(default)]
+    pub flagged: bool,
+    #[serde(default, deserialize_with = "null_default")]
+    pub body: String,
+    /// The body was extracted from an HTML part and stripped to text.
+    #[serde(default)]
+    pub body_is_html: bool,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub content_type: String,
+    /// Size in bytes.
+    #[serde(default)]
+    pub size: u64,
+    /// Set when `saveAttachments` was requested and the save succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    /// Set when the attachment was too large or could not be saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    #[serde(default, deserialize_with = "null_default")]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub display_name: String,
+    #[serde(default, alias = "primaryEmail", deserialize_with = "null_default")]
+    pub email: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub first_name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub last_name: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub address_book: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Calendar {
+    #[serde(default, deserialize_with = "null_default")]
+    pub id: String,
+    #[serde(default, deserialize_with = "null_default")]
+    pub name: String,
+    #[serde(rename = "type", default, deserialize_with = "null_default")]
+    pub calendar_type: String,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// Result of `updateMessage`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateResult {
+    #[serde(default)]
+    pub success: bool,
+    /// Human-readable list of what was done, e.g. `["marked read", "trashed"]`.
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+/// Result of the tools that open a window for review: compose tools and `createEvent`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeResult {
+    #[serde(default)]
+    pub success: bool,
+    #[serde(default, deserialize_with = "null_default")]
+    pub message: String,
+}
+
+/// How far along a long-running call is, from `getProgress`: folders searched,
+/// attachments saved.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    #[serde(default)]
+    pub progress: u64,
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// --- Arguments ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(format!("invalid sort order '{}', expected asc or desc", s)),
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchArgs {
+    /// Matched against subject, author, recipients and CC. Empty matches all.
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_results: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<SortOrder>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetMessageArgs {
+    pub message_id: String,
+    pub folder_path: String,
+    #[serde(default)]
+    pub save_attachments: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListFoldersArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMessageArgs {
+    pub message_id: String,
+    pub folder_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flagged: Option<bool>,
+    /// Destination folder URI. Ignored if `trash` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub move_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendMailArgs {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bcc: Option<String>,
+    /// Identity email address or identity ID from `listAccounts`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub is_html: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplyArgs {
+    pub message_id: String,
+    pub folder_path: String,
+    pub body: String,
+    #[serde(default)]
+    pub reply_all: bool,
+    #[serde(default)]
+    pub is_html: bool,
+    /// Overrides the original sender as recipient.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bcc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardArgs {
+    pub message_id: String,
+    pub folder_path: String,
+    pub to: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub is_html: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bcc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchContactsArgs {
+    pub query: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEventArgs {
+    pub title: String,
+    /// ISO 8601.
+    pub start_date: String,
+    /// Defaults to one hour (or one day for all-day events) after `start_date`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Defaults to the first writable calendar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_id: Option<String>,
+    #[serde(default)]
+    pub all_day: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn message_summary_from_extension() {
+        let msg: MessageSummary = serde_json::from_value(json!({
+            "id": "abc@example.com",
+            "subject": "Hello",
+            "author": "Alice <alice@example.com>",
+            "recipients": "bob@example.com",
+            "ccList": "",
+            "date": null,
+            "folder": "Inbox",
+            "folderPath": "imap://alice@example.com/INBOX",
+            "read": false,
+            "flagged": true
+        }))
+        .unwrap();
+        assert_eq!(msg.author, "Alice <alice@example.com>");
+        assert_eq!(msg.date, "");
+        assert_eq!(msg.folder_path, "imap://alice@example.com/INBOX");
+        assert!(!msg.read);
+        assert!(msg.flagged);
+    }
+
+    #[test]
+    fn message_with_attachments() {
+        let msg: Message = serde_json::from_value(json!({
+            "id": "abc@example.com",
+            "subject": null,
+            "body": "Hi",
+            "bodyIsHtml": false,
+            "attachments": [
+                { "name": "a.pdf", "contentType": "application/pdf", "size": 2048, "filePath": "/tmp/a.pdf" },
+                { "name": "big.iso", "contentType": "application/octet-stream", "size": 0, "error": "Exceeds 50MB size limit" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(msg.subject, "");
+        assert_eq!(msg.attachments[0].file_path.as_deref(), Some("/tmp/a.pdf"));
+        assert_eq!(
+            msg.attachments[1].error.as_deref(),
+            Some("Exceeds 50MB size limit")
+        );
+    }
+
+    #[test]
+    fn args_serialize_camel_case_and_skip_unset() {
+        let args = SearchArgs {
+            query: "report".into(),
+            max_results: Some(5),
+            sort_order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&args).unwrap(),
+            json!({ "query": "report", "maxResults": 5, "sortOrder": "asc" })
+        );
+    }
+
+    #[test]
+    fn update_args_only_send_requested_changes() {
+        let args = UpdateMessageArgs {
+            message_id: "abc".into(),
+            folder_path: "mailbox://x/Inbox".into(),
+            trash: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&args).unwrap(),
+            json!({ "messageId": "abc", "folderPath": "mailbox://x/Inbox", "trash": true })
+        );
+    }
+
+    #[test]
+    fn sort_order_parse() {
+        assert_eq!("DESC".parse::<SortOrder>(), Ok(SortOrder::Desc));
+        assert!("newest".parse::<SortOrder>().is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use crate::error::ThunderbirdError;
+
+/// Tools that only read, and so can be sent again after a failed attempt.
+/// Compose tools, `createEvent` and `updateMessage` are never retried.
+pub const IDEMPOTENT_TOOLS: &[&str] = &[
+    "listTools",
+    "listAccounts",
+    "searchMessages",
+    "getMessage",
+    "listFolders",
+    "searchContacts",
+    "listCalendars",
+];
+
+pub fn is_idempotent(tool: &str) -> bool {
+    IDEMPOTENT_TOOLS.contains(&tool)
+}
+
+/// Exponential backoff for idempotent calls while Thunderbird is not reachable,
+/// e.g. during a restart or while the extension's server is starting.
+///
+/// Delays double from `initial_delay` up to `max_delay`. No attempt is started
+/// once `max_elapsed` has passed since the first one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub max_elapsed: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// Fail on the first error.
+    pub fn none() -> Self {
+        Self::with_max_elapsed(Duration::ZERO)
+    }
+
+    pub fn with_max_elapsed(max_elapsed: Duration) -> Self {
+        Self {
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+            max_elapsed,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.max_elapsed.is_zero()
+    }
+
+    /// Whether a failed call to `tool` with this error should be attempted again.
+    /// Only connection failures are retried: a timeout may mean the extension
+    /// is still working on the first request.
+    pub fn should_retry(&self, tool: &str, error: &ThunderbirdError) -> bool {
+        self.is_enabled()
+            && is_idempotent(tool)
+            && matches!(error, ThunderbirdError::NotRunning { .. })
+    }
+
+    /// Start timing a call. Create this before the first attempt.
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: self.clone(),
+            start: Instant::now(),
+            next: self.initial_delay,
+        }
+    }
+}
+
+/// Backoff state for one call.
+#[derive(Debug)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    start: Instant,
+    next: Duration,
+}
+
+impl Backoff {
+    /// How long to wait before the next attempt, or `None` if the time budget is spent.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        self.delay_after(self.start.elapsed())
+    }
+
+    fn delay_after(&mut self, elapsed: Duration) -> Option<Duration> {
+        let remaining = self.policy.max_elapsed.checked_sub(elapsed)?;
+        if remaining.is_zero() {
+            return None;
+        }
+        let delay = self.next.min(remaining);
+        self.next = (self.next * 2).min(self.policy.max_delay);
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn not_running() -> ThunderbirdError {
+        ThunderbirdError::NotRunning {
+            endpoint: "http://localhost:8756/".into(),
+            message: "Connection refused".into(),
+        }
+    }
+
+    #[test]
+    fn delays_double_up_to_max() {
+        let mut backoff = RetryPolicy::with_max_elapsed(Duration::from_secs(60)).backoff();
+        let delays: Vec<u64> = (0..7)
+            .map(|_| backoff.delay_after(Duration::ZERO).unwrap().as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [250, 500, 1000, 2000, 4000, 4000, 4000]);
+    }
+
+    #[test]
+    fn stops_when_budget_spent() {
+        let mut backoff = RetryPolicy::with_max_elapsed(Duration::from_secs(1)).backoff();
+        assert_eq!(
+            backoff.delay_after(Duration::from_millis(900)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(backoff.delay_after(Duration::from_secs(1)), None);
+        assert_eq!(backoff.delay_after(Duration::from_secs(2)), None);
+    }
+
+    #[test]
+    fn only_idempotent_connection_failures_retry() {
+        let policy = RetryPolicy::with_max_elapsed(Duration::from_secs(10));
+        assert!(policy.should_retry("searchMessages", &not_running()));
+        assert!(!policy.should_retry("updateMessage", &not_running()));
+        assert!(!policy.should_retry("sendMail", &not_running()));
+        assert!(!policy.should_retry(
+            "getMessage",
+            &ThunderbirdError::tool("Message not found: x")
+        ));
+        assert!(!RetryPolicy::none().should_retry("searchMessages", &not_running()));
+    }
+}
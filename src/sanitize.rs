@@ -1,46 +1,61 @@
+use std::borrow::Cow;
+
+/// Decode a response body as UTF-8, replacing invalid sequences with U+FFFD.
+/// Mis-encoded email bodies are common enough that failing the whole response
+/// is worse than losing a few characters. The flag says whether anything was
+/// replaced; valid input is borrowed, not copied.
+pub fn decode_lossy(data: &[u8]) -> (Cow<'_, str>, bool) {
+    let text = String::from_utf8_lossy(data);
+    let replaced = matches!(text, Cow::Owned(_));
+    (text, replaced)
+}
+
 /// Sanitize JSON that may contain invalid control characters.
 /// Email bodies often contain raw control chars that break JSON parsing.
 ///
-/// Pass 1: Remove control chars except \n, \r, \t.
-/// Pass 2: Escape raw (unescaped) \r, \n, \t with proper backslash parity tracking.
-pub fn sanitize_json(data: &str) -> String {
-    // Pass 1: remove control chars except \n (0x0A), \r (0x0D), \t (0x09)
-    let pass1: String = data
-        .chars()
-        .filter(|&ch| {
-            if ch.is_control() {
-                matches!(ch, '\n' | '\r' | '\t')
-            } else {
-                true
-            }
-        })
-        .collect();
-
-    // Pass 2: escape raw newlines/carriage returns/tabs that aren't already escaped.
+/// Control chars other than \n, \r, \t are removed. Raw (unescaped) \r, \n, \t
+/// are escaped, with backslash parity tracking. Works in one pass and only
+/// allocates once it finds something to change, so multi-megabyte bodies that
+/// are already clean cost nothing.
+pub fn sanitize_json(data: &str) -> Cow<'_, str> {
+    let mut out: Option<String> = None;
     // Track backslash parity via toggle (not assign) to handle \\<LF> correctly.
-    let mut result = String::with_capacity(pass1.len());
+    // Removed control chars do not affect it.
     let mut prev_backslash = false;
 
-    for ch in pass1.chars() {
-        if ch == '\\' {
+    for (i, ch) in data.char_indices() {
+        let replacement = if ch.is_control() && !matches!(ch, '\n' | '\r' | '\t') {
+            Some("")
+        } else if ch == '\\' {
             prev_backslash = !prev_backslash; // TOGGLE, not assign
-            result.push(ch);
-        } else if !prev_backslash {
-            match ch {
-                '\n' => result.push_str("\\n"),
-                '\r' => result.push_str("\\r"),
-                '\t' => result.push_str("\\t"),
-                _ => result.push(ch),
-            }
-            // prev_backslash is already false
-        } else {
+            None
+        } else if prev_backslash {
             // preceded by odd number of backslashes - already escaped
-            result.push(ch);
             prev_backslash = false;
+            None
+        } else {
+            match ch {
+                '\n' => Some("\\n"),
+                '\r' => Some("\\r"),
+                '\t' => Some("\\t"),
+                _ => None,
+            }
+        };
+
+        match (replacement, &mut out) {
+            (None, None) => {}
+            (None, Some(result)) => result.push(ch),
+            (Some(r), Some(result)) => result.push_str(r),
+            (Some(r), None) => {
+                let mut result = String::with_capacity(data.len() + 64);
+                result.push_str(&data[..i]);
+                result.push_str(r);
+                out = Some(result);
+            }
         }
     }
 
-    result
+    out.map_or(Cow::Borrowed(data), Cow::Owned)
 }
 
 #[cfg(test)]
@@ -110,6 +125,29 @@ mod tests {
         assert_eq!(sanitize_json(""), "");
     }
 
+    #[test]
+    fn clean_input_is_borrowed() {
+        let input = r#"{"body": "line1\\nline2"}"#;
+        assert!(matches!(sanitize_json(input), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn removed_control_char_keeps_backslash_parity() {
+        // The removed \x01 sits between the backslash and the newline it escapes.
+        assert_eq!(sanitize_json("a\\\x01\nb"), "a\\\nb");
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let (text, replaced) = decode_lossy(b"caf\xe9 ok");
+        assert_eq!(text, "caf\u{FFFD} ok");
+        assert!(replaced);
+
+        let (text, replaced) = decode_lossy("café".as_bytes());
+        assert!(matches!(text, Cow::Borrowed("café")));
+        assert!(!replaced);
+    }
+
     #[test]
     fn only_control_chars() {
         let input = "\x00\x01\x02\x03";
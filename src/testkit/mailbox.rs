@@ -0,0 +1,547 @@
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::model::{
+    Account, Attachment, Calendar, Contact, CreateEventArgs, Folder, ForwardArgs, GetMessageArgs,
+    Identity, ListFoldersArgs, Message, MessageSummary, ReplyArgs, SearchArgs, SearchContactsArgs,
+    SendMailArgs, SortOrder, UpdateMessageArgs,
+};
+
+// Same limits as extension/mcp_server/api.js.
+const DEFAULT_MAX_RESULTS: usize = 50;
+const MAX_SEARCH_RESULTS_CAP: usize = 200;
+
+/// A message and the URI of the folder it is in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredMessage {
+    pub folder_path: String,
+    pub message: Message,
+}
+
+/// In-memory mail store behind [`FakeThunderbird`](super::FakeThunderbird).
+/// Tools behave like the extension's, including its `{ "error": ... }` results
+/// and error wording, but compose tools only record what they were asked to do.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mailbox {
+    pub accounts: Vec<Account>,
+    /// Message counts are computed from `messages` when listed.
+    pub folders: Vec<Folder>,
+    pub messages: Vec<StoredMessage>,
+    pub contacts: Vec<Contact>,
+    pub calendars: Vec<Calendar>,
+    /// Calls to compose tools and `createEvent`, in order, as (tool, arguments).
+    pub composed: Vec<(String, Value)>,
+}
+
+pub const INBOX: &str = "imap://alice%40example.com@imap.example.com/INBOX";
+pub const ARCHIVE: &str = "imap://alice%40example.com@imap.example.com/Archive";
+pub const TRASH: &str = "imap://alice%40example.com@imap.example.com/Trash";
+pub const LOCAL_INBOX: &str = "mailbox://nobody@Local%20Folders/Inbox";
+
+impl Mailbox {
+    /// One IMAP account with Inbox, Archive and Trash, a Local Folders account,
+    /// a handful of messages, contacts and calendars.
+    pub fn fixture() -> Self {
+        let folder = |name: &str, path: &str, account: &str| Folder {
+            name: name.into(),
+            path: path.into(),
+            account_id: account.into(),
+            ..Default::default()
+        };
+        let message =
+            |folder_path: &str, id: &str, subject: &str, author: &str, date: &str| StoredMessage {
+                folder_path: folder_path.into(),
+                message: Message {
+                    id: id.into(),
+                    subject: subject.into(),
+                    author: author.into(),
+                    recipients: "Alice Example <alice@example.com>".into(),
+                    date: date.into(),
+                    body: format!("{}\n\n-- \n{}", subject, author),
+                    read: true,
+                    ..Default::default()
+                },
+            };
+
+        let mut report = message(
+            INBOX,
+            "report-q1@example.com",
+            "Quarterly report",
+            "Bob Builder <bob@example.com>",
+            "2026-02-19T14:30:00.000Z",
+        );
+        report.message.read = false;
+        report.message.cc_list = "carol@example.com".into();
+        report.message.attachments = vec![Attachment {
+            name: "report.pdf".into(),
+            content_type: "application/pdf".into(),
+            size: 48_213,
+            ..Default::default()
+        }];
+
+        let mut lunch = message(
+            INBOX,
+            "lunch-42@example.com",
+            "Lunch on Friday?",
+            "Carol Singer <carol@example.com>",
+            "2026-02-18T09:05:00.000Z",
+        );
+        lunch.message.flagged = true;
+
+        let mut meeting = message(
+            INBOX,
+            "meeting-7@example.com",
+            "Can we meet next week?",
+            "Dave Diver <dave@example.com>",
+            "2026-02-17T16:45:00.000Z",
+        );
+        meeting.message.read = false;
+
+        let mut html = message(
+            INBOX,
+            "newsletter-2026-02@news.example.com",
+            "February newsletter",
+            "Example News <news@news.example.com>",
+            "2026-02-01T07:00:00.000Z",
+        );
+        html.message.body = "Welcome to the February issue.".into();
+        html.message.body_is_html = true;
+
+        let old = message(
+            ARCHIVE,
+            "report-q4@example.com",
+            "Quarterly report Q4",
+            "Bob Builder <bob@example.com>",
+            "2025-11-20T10:00:00.000Z",
+        );
+        let local = message(
+            LOCAL_INBOX,
+            "note-1@localhost",
+            "Note to self",
+            "Alice Example <alice@example.com>",
+            "2026-01-05T12:00:00.000Z",
+        );
+
+        Self {
+            accounts: vec![
+                Account {
+                    id: "account1".into(),
+                    name: "alice@example.com".into(),
+                    account_type: "imap".into(),
+                    identities: vec![
+                        Identity {
+                            id: "id1".into(),
+                            email: "alice@example.com".into(),
+                            name: "Alice Example".into(),
+                            is_default: true,
+                        },
+                        Identity {
+                            id: "id2".into(),
+                            email: "alice@work.example.com".into(),
+                            name: "Alice Example (Work)".into(),
+                            is_default: false,
+                        },
+                    ],
+                },
+                Account {
+                    id: "account2".into(),
+                    name: "Local Folders".into(),
+                    account_type: "none".into(),
+                    identities: Vec::new(),
+                },
+            ],
+            folders: vec![
+                folder("Inbox", INBOX, "account1"),
+                folder("Archive", ARCHIVE, "account1"),
+                folder("Trash", TRASH, "account1"),
+                folder("Inbox", LOCAL_INBOX, "account2"),
+            ],
+            messages: vec![report, lunch, meeting, html, old, local],
+            contacts: vec![
+                Contact {
+                    id: "c1".into(),
+                    display_name: "Bob Builder".into(),
+                    email: "bob@example.com".into(),
+                    first_name: "Bob".into(),
+                    last_name: "Builder".into(),
+                    address_book: "Personal Address Book".into(),
+                },
+                Contact {
+                    id: "c2".into(),
+                    display_name: "Carol Singer".into(),
+                    email: "carol@example.com".into(),
+                    first_name: "Carol".into(),
+                    last_name: "Singer".into(),
+                    address_book: "Collected Addresses".into(),
+                },
+            ],
+            calendars: vec![
+                Calendar {
+                    id: "cal-home".into(),
+                    name: "Home".into(),
+                    calendar_type: "storage".into(),
+                    ..Default::default()
+                },
+                Calendar {
+                    id: "cal-holidays".into(),
+                    name: "Holidays".into(),
+                    calendar_type: "ics".into(),
+                    read_only: true,
+                    ..Default::default()
+                },
+            ],
+            composed: Vec::new(),
+        }
+    }
+
+    pub fn find(&self, message_id: &str, folder_path: &str) -> Option<&StoredMessage> {
+        self.messages
+            .iter()
+            .find(|m| m.message.id == message_id && m.folder_path == folder_path)
+    }
+
+    /// Run a tool. `Err` becomes a JSON-RPC error, like an exception in the extension.
+    pub fn call(&mut self, tool: &str, params: &Value) -> Result<Value, String> {
+        match tool {
+            "listTools" => Ok(json!({ "tools": tool_list() })),
+            "listAccounts" => Ok(json!(self.accounts)),
+            "searchMessages" => Ok(self.search_messages(&args(params)?)),
+            "getMessage" => Ok(self.get_message(&args(params)?)),
+            "listFolders" => Ok(self.list_folders(&args(params)?)),
+            "updateMessage" => Ok(self.update_message(&args(params)?)),
+            "searchContacts" => Ok(self.search_contacts(&args(params)?)),
+            "listCalendars" => Ok(json!(self.calendars)),
+            "sendMail" => {
+                args::<SendMailArgs>(params)?;
+                Ok(self.compose(tool, params, "Compose window opened".into()))
+            }
+            "replyToMessage" => {
+                let a: ReplyArgs = args(params)?;
+                Ok(match self.find(&a.message_id, &a.folder_path) {
+                    Some(_) => self.compose(tool, params, "Reply window opened".into()),
+                    None => self.not_found(&a.message_id, &a.folder_path),
+                })
+            }
+            "forwardMessage" => {
+                let a: ForwardArgs = args(params)?;
+                Ok(match self.find(&a.message_id, &a.folder_path) {
+                    Some(m) => {
+                        let count = m.message.attachments.len() + a.attachments.len();
+                        let message = format!("Forward window opened with {} attachment(s)", count);
+                        self.compose(tool, params, message)
+                    }
+                    None => self.not_found(&a.message_id, &a.folder_path),
+                })
+            }
+            "createEvent" => Ok(self.create_event(&args(params)?, params)),
+            _ => Err(format!("Error: Unknown tool: {}", tool)),
+        }
+    }
+
+    fn folder(&self, path: &str) -> Option<&Folder> {
+        self.folders.iter().find(|f| f.path == path)
+    }
+
+    fn not_found(&self, message_id: &str, folder_path: &str) -> Value {
+        if self.folder(folder_path).is_none() {
+            json!({ "error": format!("Folder not found: {}", folder_path) })
+        } else {
+            json!({ "error": format!("Message not found: {}", message_id) })
+        }
+    }
+
+    fn summary(&self, stored: &StoredMessage) -> MessageSummary {
+        let m = &stored.message;
+        MessageSummary {
+            id: m.id.clone(),
+            subject: m.subject.clone(),
+            author: m.author.clone(),
+            recipients: m.recipients.clone(),
+            cc_list: m.cc_list.clone(),
+            date: m.date.clone(),
+            folder: self
+                .folder(&stored.folder_path)
+                .map(|f| f.name.clone())
+                .unwrap_or_default(),
+            folder_path: stored.folder_path.clone(),
+            read: m.read,
+            flagged: m.flagged,
+        }
+    }
+
+    fn search_messages(&self, args: &SearchArgs) -> Value {
+        let query = args.query.to_lowercase();
+        let mut hits: Vec<&StoredMessage> = self
+            .messages
+            .iter()
+            .filter(|s| {
+                let m = &s.message;
+                [&m.subject, &m.author, &m.recipients, &m.cc_list]
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(&query))
+            })
+            .filter(|s| {
+                args.start_date
+                    .as_deref()
+                    .is_none_or(|start| s.message.date.as_str() >= start)
+            })
+            .filter(|s| {
+                args.end_date.as_deref().is_none_or(|end| {
+                    // A date-only end includes that whole day, as in the extension.
+                    let date = if end.contains('T') {
+                        s.message.date.as_str()
+                    } else {
+                        s.message.date.get(..10).unwrap_or("")
+                    };
+                    date <= end
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.message.date.cmp(&a.message.date));
+        if args.sort_order == Some(SortOrder::Asc) {
+            hits.reverse();
+        }
+        let limit = args
+            .max_results
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_RESULTS)
+            .min(MAX_SEARCH_RESULTS_CAP);
+
+        let summaries: Vec<MessageSummary> = hits
+            .into_iter()
+            .take(limit)
+            .map(|s| self.summary(s))
+            .collect();
+        json!(summaries)
+    }
+
+    fn get_message(&self, args: &GetMessageArgs) -> Value {
+        let Some(stored) = self.find(&args.message_id, &args.folder_path) else {
+            return self.not_found(&args.message_id, &args.folder_path);
+        };
+        let mut message = stored.message.clone();
+        if args.save_attachments {
+            // Reported as saved; nothing is written to disk.
+            for att in &mut message.attachments {
+                att.file_path = Some(format!("/tmp/thunderbird-api/{}", att.name));
+            }
+        }
+        json!(message)
+    }
+
+    fn list_folders(&self, args: &ListFoldersArgs) -> Value {
+        let folders: Vec<Folder> = self
+            .folders
+            .iter()
+            .filter(|f| args.account_id.as_ref().is_none_or(|a| &f.account_id == a))
+            .map(|f| {
+                let in_folder = || self.messages.iter().filter(|m| m.folder_path == f.path);
+                Folder {
+                    total_messages: in_folder().count() as u64,
+                    unread_messages: in_folder().filter(|m| !m.message.read).count() as u64,
+                    ..f.clone()
+                }
+            })
+            .collect();
+        json!(folders)
+    }
+
+    fn update_message(&mut self, args: &UpdateMessageArgs) -> Value {
+        let Some(index) = self
+            .messages
+            .iter()
+            .position(|m| m.message.id == args.message_id && m.folder_path == args.folder_path)
+        else {
+            return self.not_found(&args.message_id, &args.folder_path);
+        };
+
+        let mut actions = Vec::new();
+        if let Some(read) = args.read {
+            self.messages[index].message.read = read;
+            actions.push(if read { "marked read" } else { "marked unread" }.to_string());
+        }
+        if let Some(flagged) = args.flagged {
+            self.messages[index].message.flagged = flagged;
+            actions.push(if flagged { "flagged" } else { "unflagged" }.to_string());
+        }
+
+        let trash = args.trash == Some(true);
+        if trash || args.move_to.is_some() {
+            let dest = if trash {
+                let account = self
+                    .folder(&args.folder_path)
+                    .map(|f| f.account_id.clone())
+                    .unwrap_or_default();
+                match self
+                    .folders
+                    .iter()
+                    .find(|f| f.account_id == account && f.name == "Trash")
+                {
+                    Some(f) => f.clone(),
+                    None => return json!({ "error": "Trash folder not found for this account" }),
+                }
+            } else {
+                let uri = args.move_to.as_deref().unwrap_or_default();
+                match self.folder(uri) {
+                    Some(f) => f.clone(),
+                    None => {
+                        return json!({ "error": format!("Destination folder not found: {}", uri) })
+                    }
+                }
+            };
+
+            if dest.path == args.folder_path {
+                actions.push("already in destination folder".into());
+            } else {
+                self.messages[index].folder_path = dest.path.clone();
+                actions.push(if trash {
+                    "trashed".into()
+                } else {
+                    format!("moved to {}", dest.name)
+                });
+            }
+        }
+
+        if actions.is_empty() {
+            actions.push("no changes requested".into());
+        }
+        json!({ "success": true, "actions": actions })
+    }
+
+    fn search_contacts(&self, args: &SearchContactsArgs) -> Value {
+        let query = args.query.to_lowercase();
+        let contacts: Vec<&Contact> = self
+            .contacts
+            .iter()
+            .filter(|c| {
+                [&c.email, &c.display_name, &c.first_name, &c.last_name]
+                    .iter()
+                    .any(|field| field.to_lowercase().contains(&query))
+            })
+            .take(DEFAULT_MAX_RESULTS)
+            .collect();
+        json!(contacts)
+    }
+
+    fn create_event(&mut self, args: &CreateEventArgs, params: &Value) -> Value {
+        if args.start_date.len() < 10 {
+            return json!({ "error": format!("Invalid startDate: {}", args.start_date) });
+        }
+        let calendar = match &args.calendar_id {
+            Some(id) => match self.calendars.iter().find(|c| &c.id == id) {
+                Some(c) if c.read_only => {
+                    return json!({ "error": format!("Calendar is read-only: {}", c.name) })
+                }
+                Some(c) => c,
+                None => return json!({ "error": format!("Calendar not found: {}", id) }),
+            },
+            None => match self.calendars.iter().find(|c| !c.read_only) {
+                Some(c) => c,
+                None => return json!({ "error": "No writable calendar found" }),
+            },
+        };
+        let message = format!(
+            "Event dialog opened for \"{}\" on calendar \"{}\"",
+            args.title, calendar.name
+        );
+        self.compose("createEvent", params, message)
+    }
+
+    fn compose(&mut self, tool: &str, params: &Value, message: String) -> Value {
+        self.composed.push((tool.to_string(), params.clone()));
+        json!({ "success": true, "message": message })
+    }
+}
+
+fn args<T: DeserializeOwned>(params: &Value) -> Result<T, String> {
+    serde_json::from_value(params.clone()).map_err(|e| format!("Error: Invalid params: {}", e))
+}
+
+/// The extension's `listTools` answer, with input schemas trimmed to the properties.
+pub fn tool_list() -> Value {
+    let tool = |name: &str, title: &str, description: &str, props: Value, required: &[&str]| {
+        json!({
+            "name": name,
+            "title": title,
+            "description": description,
+            "inputSchema": { "type": "object", "properties": props, "required": required },
+        })
+    };
+    let s = |description: &str| json!({ "type": "string", "description": description });
+    let b = |description: &str| json!({ "type": "boolean", "description": description });
+    let list = |description: &str| json!({ "type": "array", "items": { "type": "string" }, "description": description });
+
+    json!([
+        tool("listAccounts", "List Accounts", "List all email accounts and their identities", json!({}), &[]),
+        tool("searchMessages", "Search Mail", "Search message headers and return IDs/folder paths you can use with getMessage to read full email content", json!({
+            "query": s("Text to search in subject, author, or recipients (use empty string to match all)"),
+            "startDate": s("Filter messages on or after this ISO 8601 date"),
+            "endDate": s("Filter messages on or before this ISO 8601 date"),
+            "maxResults": { "type": "number", "description": "Maximum number of results to return (default 50, max 200)" },
+            "sortOrder": s("Date sort order: asc (oldest first) or desc (newest first, default)"),
+        }), &["query"]),
+        tool("getMessage", "Get Message", "Read the full content of an email message by its ID, with optional attachment saving to disk", json!({
+            "messageId": s("The message ID (from searchMessages results)"),
+            "folderPath": s("The folder URI path (from searchMessages results)"),
+            "saveAttachments": b("Save attachments to temp files and return file paths (default: false, returns metadata only)"),
+        }), &["messageId", "folderPath"]),
+        tool("sendMail", "Compose Mail", "Open a compose window with pre-filled recipient, subject, and body for user review before sending", json!({
+            "to": s("Recipient email address"),
+            "subject": s("Email subject line"),
+            "body": s("Email body text"),
+            "cc": s("CC recipients (comma-separated)"),
+            "bcc": s("BCC recipients (comma-separated)"),
+            "isHtml": b("Set to true if body contains HTML markup (default: false)"),
+            "from": s("Sender identity (email address or identity ID from listAccounts)"),
+            "attachments": list("Array of file paths to attach"),
+        }), &["to", "subject", "body"]),
+        tool("listCalendars", "List Calendars", "Return the user's calendars", json!({}), &[]),
+        tool("createEvent", "Create Event", "Open a pre-filled event dialog in Thunderbird for user review before saving", json!({
+            "title": s("Event title"),
+            "startDate": s("Start date/time in ISO 8601 format"),
+            "endDate": s("End date/time in ISO 8601 (defaults to startDate + 1h for timed, +1 day for all-day)"),
+            "location": s("Event location"),
+            "description": s("Event description"),
+            "calendarId": s("Target calendar ID (from listCalendars, defaults to first writable calendar)"),
+            "allDay": b("Create an all-day event (default: false)"),
+        }), &["title", "startDate"]),
+        tool("searchContacts", "Search Contacts", "Find contacts the user interacted with", json!({
+            "query": s("Email address or name to search for"),
+        }), &["query"]),
+        tool("replyToMessage", "Reply to Message", "Open a reply compose window for a specific message with proper threading", json!({
+            "messageId": s("The message ID to reply to (from searchMessages results)"),
+            "folderPath": s("The folder URI path (from searchMessages results)"),
+            "body": s("Reply body text"),
+            "replyAll": b("Reply to all recipients (default: false)"),
+            "isHtml": b("Set to true if body contains HTML markup (default: false)"),
+            "to": s("Override recipient email (default: original sender)"),
+            "cc": s("CC recipients (comma-separated)"),
+            "bcc": s("BCC recipients (comma-separated)"),
+            "from": s("Sender identity (email address or identity ID from listAccounts)"),
+            "attachments": list("Array of file paths to attach"),
+        }), &["messageId", "folderPath", "body"]),
+        tool("forwardMessage", "Forward Message", "Open a forward compose window for a message with attachments preserved", json!({
+            "messageId": s("The message ID to forward (from searchMessages results)"),
+            "folderPath": s("The folder URI path (from searchMessages results)"),
+            "to": s("Recipient email address"),
+            "body": s("Additional text to prepend (optional)"),
+            "isHtml": b("Set to true if body contains HTML markup (default: false)"),
+            "cc": s("CC recipients (comma-separated)"),
+            "bcc": s("BCC recipients (comma-separated)"),
+            "from": s("Sender identity (email address or identity ID from listAccounts)"),
+            "attachments": list("Array of additional file paths to attach"),
+        }), &["messageId", "folderPath", "to"]),
+        tool("listFolders", "List Folders", "List all mail folders with URIs and message counts", json!({
+            "accountId": s("Filter to a specific account ID (optional)"),
+        }), &[]),
+        tool("updateMessage", "Update Message", "Mark a message as read/unread, flag/unflag, move to another folder, or trash it", json!({
+            "messageId": s("The message ID (from searchMessages results)"),
+            "folderPath": s("The folder URI path (from searchMessages results)"),
+            "read": b("Mark as read (true) or unread (false)"),
+            "flagged": b("Mark as flagged (true) or unflagged (false)"),
+            "moveTo": s("Folder URI to move the message to (from listFolders)"),
+            "trash": b("Move the message to the Trash folder"),
+        }), &["messageId", "folderPath"]),
+    ])
+}
@@ -0,0 +1,446 @@
+mod mailbox;
+
+pub use mailbox::{tool_list, Mailbox, StoredMessage, ARCHIVE, INBOX, LOCAL_INBOX, TRASH};
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::client::ThunderbirdClient;
+use crate::config::ClientConfig;
+use crate::model::Progress;
+use crate::types::{JsonRpcRequest, JsonRpcResponse};
+
+/// Something to go wrong when the fake extension answers a tool.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Wait this long before answering.
+    Delay(Duration),
+    /// Take `steps` steps of `step` each before answering, reporting them
+    /// through `getProgress` like a search over many folders.
+    Progress { steps: u64, step: Duration },
+    /// Put raw newlines, tabs and other control characters inside string
+    /// values, as mis-encoded mail bodies do.
+    ControlChars,
+    /// Put bytes that are not valid UTF-8 inside string values.
+    InvalidUtf8,
+    /// Answer with a tool-level `{ "error": ... }` result.
+    ToolError(String),
+    /// Answer with a JSON-RPC error object.
+    RpcError { code: i32, message: String },
+    /// Answer with this HTTP status and a plain-text body.
+    HttpStatus(u16),
+}
+
+#[derive(Default)]
+struct State {
+    mailbox: Mailbox,
+    faults: Vec<(String, Fault)>,
+    requests: Vec<JsonRpcRequest>,
+    token: Option<String>,
+    /// By `_meta.progressToken`, for calls with a `Progress` fault in flight.
+    progress: HashMap<String, Progress>,
+}
+
+/// An in-process stand-in for the Thunderbird extension: a JSON-RPC server on
+/// a random localhost port that serves the extension's tools over a [`Mailbox`].
+///
+/// ```no_run
+/// use thunderbird_api::testkit::{FakeThunderbird, Fault};
+///
+/// let fake = FakeThunderbird::start();
+/// fake.inject("getMessage", Fault::ControlChars);
+/// let client = fake.client();
+/// ```
+///
+/// The server stops when this value is dropped.
+pub struct FakeThunderbird {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FakeThunderbird {
+    /// Serve [`Mailbox::fixture`].
+    pub fn start() -> Self {
+        Self::with_mailbox(Mailbox::fixture())
+    }
+
+    pub fn with_mailbox(mailbox: Mailbox) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind fake Thunderbird");
+        let addr = listener.local_addr().expect("fake Thunderbird address");
+        let state = Arc::new(Mutex::new(State {
+            mailbox,
+            ..Default::default()
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (accept_state, accept_stop) = (state.clone(), stop.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let state = accept_state.clone();
+                thread::spawn(move || serve_connection(stream, &state));
+            }
+        });
+
+        Self { addr, state, stop }
+    }
+
+    /// URL to pass as `--endpoint`.
+    pub fn endpoint(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// A client for this server, with no retries and a 5 second timeout.
+    pub fn client(&self) -> ThunderbirdClient {
+        ThunderbirdClient::with_config(&ClientConfig {
+            endpoint: self.endpoint(),
+            timeout: Duration::from_secs(5),
+            token: self.lock().token.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Reject requests without `Authorization: Bearer <token>`, like the extension.
+    pub fn require_token(&self, token: impl Into<String>) {
+        self.lock().token = Some(token.into());
+    }
+
+    /// Apply `fault` to every call of `tool` until [`FakeThunderbird::clear_faults`].
+    /// `"*"` matches every method. Faults for the same call apply in order.
+    pub fn inject(&self, tool: impl Into<String>, fault: Fault) {
+        self.lock().faults.push((tool.into(), fault));
+    }
+
+    pub fn clear_faults(&self) {
+        self.lock().faults.clear();
+    }
+
+    /// Every request received so far.
+    pub fn requests(&self) -> Vec<JsonRpcRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Current state of the mailbox, including moves and compose calls.
+    pub fn mailbox(&self) -> Mailbox {
+        self.lock().mailbox.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for FakeThunderbird {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Serve HTTP/1.1 requests on one keep-alive connection until the client closes it.
+fn serve_connection(stream: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(match stream.try_clone() {
+        Ok(s) => s,
+        Err(_) => return,
+    });
+    let mut writer = stream;
+    while let Some(request) = read_request(&mut reader) {
+        let (status, body) = respond(&request, state);
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            405 => "Method Not Allowed",
+            _ => "Error",
+        };
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\n\r\n",
+            status,
+            reason,
+            body.len()
+        );
+        if writer
+            .write_all(head.as_bytes())
+            .and_then(|_| writer.write_all(&body))
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<HttpRequest> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok()? == 0 {
+        return None;
+    }
+    let method = line.split_whitespace().next()?.to_string();
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().ok()?,
+            "authorization" => authorization = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some(HttpRequest {
+        method,
+        authorization,
+        body,
+    })
+}
+
+/// Mirrors the request handler in extension/mcp_server/api.js.
+fn respond(request: &HttpRequest, state: &Mutex<State>) -> (u16, Vec<u8>) {
+    if request.method != "POST" {
+        return (405, b"POST only".to_vec());
+    }
+
+    let lock = || state.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(token) = lock().token.clone() {
+        match request.authorization.as_deref() {
+            None => return (401, b"Missing API token".to_vec()),
+            Some(auth) if auth != format!("Bearer {}", token) => {
+                return (401, b"Invalid API token".to_vec())
+            }
+            Some(_) => {}
+        }
+    }
+
+    let Ok(rpc) = serde_json::from_slice::<JsonRpcRequest>(&request.body) else {
+        return (400, b"Invalid JSON".to_vec());
+    };
+
+    let faults: Vec<Fault> = {
+        let mut state = lock();
+        state.requests.push(rpc.clone());
+        state
+            .faults
+            .iter()
+            .filter(|(tool, _)| tool == "*" || *tool == rpc.method)
+            .map(|(_, fault)| fault.clone())
+            .collect()
+    };
+
+    let params = rpc.params.clone().unwrap_or_else(|| json!({}));
+    if rpc.method == "getProgress" {
+        let token = params["progressToken"].as_str().unwrap_or_default();
+        let progress = lock().progress.get(token).cloned();
+        let response = JsonRpcResponse::success(rpc.id.clone(), json!(progress));
+        return (200, serde_json::to_vec(&response).unwrap_or_default());
+    }
+
+    // Delays happen outside the lock so other calls are not held up.
+    for fault in &faults {
+        match fault {
+            Fault::Delay(delay) => thread::sleep(*delay),
+            Fault::Progress { steps, step } => {
+                let token = params["_meta"]["progressToken"].as_str().map(String::from);
+                for done in 0..*steps {
+                    if let Some(token) = &token {
+                        let progress = Progress {
+                            progress: done,
+                            total: Some(*steps),
+                            message: Some(format!("Searched {} of {} folders", done, steps)),
+                        };
+                        lock().progress.insert(token.clone(), progress);
+                    }
+                    thread::sleep(*step);
+                }
+                if let Some(token) = &token {
+                    lock().progress.remove(token);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut response = match lock().mailbox.call(&rpc.method, &params) {
+        Ok(result) => JsonRpcResponse::success(rpc.id.clone(), result),
+        Err(message) => JsonRpcResponse::error(rpc.id.clone(), -32000, message),
+    };
+
+    let mut corrupt = None;
+    for fault in faults {
+        match fault {
+            Fault::Delay(_) | Fault::Progress { .. } => {}
+            Fault::HttpStatus(status) => {
+                return (status, format!("Injected HTTP {}", status).into_bytes())
+            }
+            Fault::ToolError(message) => {
+                response = JsonRpcResponse::success(rpc.id.clone(), json!({ "error": message }))
+            }
+            Fault::RpcError { code, message } => {
+                response = JsonRpcResponse::error(rpc.id.clone(), code, message)
+            }
+            Fault::ControlChars | Fault::InvalidUtf8 => corrupt = Some(fault),
+        }
+    }
+
+    match (corrupt, response.result.as_mut()) {
+        (Some(fault), Some(result)) => {
+            mark_strings(result, "");
+            let json = serde_json::to_string(&response).unwrap_or_default();
+            (200, corrupt_marked(&json, &fault))
+        }
+        _ => (200, serde_json::to_vec(&response).unwrap_or_default()),
+    }
+}
+
+// Private-use characters that survive serialization unescaped and are then
+// swapped for the raw bytes a fault needs.
+const NEWLINE_MARK: char = '\u{E000}';
+const CONTROL_MARK: char = '\u{E001}';
+
+/// Mark text fields for corruption. Ids and folder URIs are left alone so the
+/// caller can still follow up on the results.
+fn mark_strings(value: &mut Value, key: &str) {
+    match value {
+        Value::String(s) => {
+            let keep = key == "id" || key == "path" || key.ends_with("Id") || key.ends_with("Path");
+            if !keep && !s.is_empty() {
+                *s = format!(
+                    "{}{}{}",
+                    s.replace('\n', &NEWLINE_MARK.to_string()),
+                    CONTROL_MARK,
+                    NEWLINE_MARK
+                );
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| mark_strings(v, key)),
+        Value::Object(map) => map.iter_mut().for_each(|(k, v)| mark_strings(v, k)),
+        _ => {}
+    }
+}
+
+fn corrupt_marked(json: &str, fault: &Fault) -> Vec<u8> {
+    let mut out = Vec::with_capacity(json.len());
+    let mut buf = [0; 4];
+    for ch in json.chars() {
+        match (ch, fault) {
+            (NEWLINE_MARK, Fault::ControlChars) => out.extend_from_slice(b"\n\t"),
+            (CONTROL_MARK, Fault::ControlChars) => out.extend_from_slice(b"\x01\x1b"),
+            (NEWLINE_MARK, _) => out.extend_from_slice(b"\\n"),
+            // Latin-1 "é" and a stray continuation byte.
+            (CONTROL_MARK, _) => out.extend_from_slice(b"\xe9\x80"),
+            _ => out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ThunderbirdError, ToolErrorCode};
+    use crate::model::{GetMessageArgs, SearchArgs, UpdateMessageArgs};
+
+    fn get(id: &str, folder: &str) -> GetMessageArgs {
+        GetMessageArgs {
+            message_id: id.into(),
+            folder_path: folder.into(),
+            save_attachments: false,
+        }
+    }
+
+    #[test]
+    fn serves_fixture_mailbox() {
+        let fake = FakeThunderbird::start();
+        let client = fake.client();
+        let hits = client
+            .search_messages(&SearchArgs {
+                query: "quarterly".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, "report-q1@example.com");
+        assert_eq!(fake.requests()[0].method, "searchMessages");
+    }
+
+    #[test]
+    fn trash_moves_message() {
+        let fake = FakeThunderbird::start();
+        let result = fake
+            .client()
+            .update_message(&UpdateMessageArgs {
+                message_id: "lunch-42@example.com".into(),
+                folder_path: INBOX.into(),
+                trash: Some(true),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(result.actions, ["trashed"]);
+        assert!(fake.mailbox().find("lunch-42@example.com", TRASH).is_some());
+    }
+
+    #[test]
+    fn control_chars_are_sanitized_by_client() {
+        let fake = FakeThunderbird::start();
+        fake.inject("getMessage", Fault::ControlChars);
+        let message = fake
+            .client()
+            .get_message(&get("lunch-42@example.com", INBOX))
+            .unwrap();
+        assert_eq!(message.id, "lunch-42@example.com");
+        assert!(message.subject.starts_with("Lunch on Friday?"));
+    }
+
+    #[test]
+    fn injected_tool_error() {
+        let fake = FakeThunderbird::start();
+        fake.inject("*", Fault::ToolError("Calendar not available".into()));
+        let err = fake.client().list_calendars().unwrap_err();
+        assert!(matches!(
+            err,
+            ThunderbirdError::Tool {
+                code: ToolErrorCode::Unavailable,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn token_is_checked() {
+        let fake = FakeThunderbird::start();
+        let unauthenticated = fake.client();
+        fake.require_token("secret");
+        let err = unauthenticated.list_accounts().unwrap_err();
+        assert!(matches!(err, ThunderbirdError::Auth(_)));
+        assert_eq!(fake.client().list_accounts().unwrap().len(), 2);
+    }
+}
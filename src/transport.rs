@@ -0,0 +1,199 @@
+use std::fmt;
+
+use crate::config::{self, ClientConfig};
+use crate::error::{Result, ThunderbirdError, INTERNAL_ERROR};
+use crate::types::{JsonRpcRequest, JsonRpcResponse};
+
+/// Carries serialized JSON-RPC between a `ThunderbirdClient` and the extension.
+///
+/// Implementations deliver one request body and return the raw response body,
+/// which need not be valid UTF-8.
+/// Parsing, sanitizing and retries are left to the client, so a transport only
+/// has to map its own failures onto [`ThunderbirdError`]: `NotRunning` when
+/// nothing is listening (this is what gets retried), `Timeout`, `Auth`, or
+/// `Protocol`. [`HttpTransport`] is the default; [`MemoryTransport`] answers
+/// in-process. Other channels, such as a Unix domain socket, only need to
+/// implement this trait.
+pub trait Transport: Send + Sync {
+    fn send(&self, body: &str) -> Result<Vec<u8>>;
+
+    /// Where requests go, for error messages.
+    fn endpoint(&self) -> &str;
+}
+
+/// JSON-RPC over HTTP POST, as served by the extension.
+pub struct HttpTransport {
+    agent: ureq::Agent,
+    url: String,
+    token: Option<String>,
+    max_response_size: u64,
+    /// The endpoint came from the extension's port file, which may be stale.
+    from_port_file: bool,
+}
+
+impl HttpTransport {
+    pub fn new(config: &ClientConfig) -> Self {
+        let agent = ureq::Agent::new_with_config(
+            ureq::config::Config::builder()
+                .http_status_as_error(false)
+                .timeout_global(Some(config.timeout))
+                .build(),
+        );
+        Self {
+            agent,
+            url: config.endpoint.clone(),
+            token: config.token.clone(),
+            max_response_size: config.max_response_size,
+            from_port_file: config::port_file_endpoint().as_ref() == Some(&config.endpoint),
+        }
+    }
+
+    fn transport_error(&self, e: ureq::Error) -> ThunderbirdError {
+        match e {
+            ureq::Error::Timeout(_) => ThunderbirdError::Timeout {
+                endpoint: self.url.clone(),
+            },
+            // Left behind by a profile that has quit, or another one started since
+            e if self.from_port_file => ThunderbirdError::NotRunning {
+                endpoint: self.url.clone(),
+                message: format!(
+                    "{}; the port was read from {}, which names the Thunderbird profile \
+                     started last",
+                    e,
+                    config::port_file_path().display()
+                ),
+            },
+            e => ThunderbirdError::NotRunning {
+                endpoint: self.url.clone(),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, body: &str) -> Result<Vec<u8>> {
+        let mut post = self.agent.post(&self.url).content_type("application/json");
+        if let Some(token) = &self.token {
+            post = post.header("Authorization", &format!("Bearer {}", token));
+        }
+        let response = post.send(body).map_err(|e| self.transport_error(e))?;
+
+        let status = response.status();
+        let data = response
+            .into_body()
+            .with_config()
+            .limit(self.max_response_size)
+            .read_to_vec()
+            .map_err(|e| match e {
+                ureq::Error::Timeout(_) => self.transport_error(e),
+                ureq::Error::BodyExceedsLimit(limit) => too_large(limit),
+                e => read_error(e),
+            })?;
+
+        check_status(status.as_u16(), &data)?;
+        Ok(data)
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+}
+
+type Handler = dyn Fn(&str) -> Result<Vec<u8>> + Send + Sync;
+
+/// Answers requests in-process, for tests and for embedders that host the
+/// tool implementations themselves.
+pub struct MemoryTransport {
+    handler: Box<Handler>,
+}
+
+impl MemoryTransport {
+    /// Answer each parsed request with `handler`.
+    pub fn new(
+        handler: impl Fn(JsonRpcRequest) -> JsonRpcResponse + Send + Sync + 'static,
+    ) -> Self {
+        Self::raw(move |body| {
+            let request: JsonRpcRequest =
+                serde_json::from_str(body).map_err(|e| ThunderbirdError::Protocol {
+                    code: INTERNAL_ERROR,
+                    message: format!("Invalid request: {}", e),
+                })?;
+            serde_json::to_vec(&handler(request)).map_err(|e| ThunderbirdError::Protocol {
+                code: INTERNAL_ERROR,
+                message: format!("Failed to serialize response: {}", e),
+            })
+        })
+    }
+
+    /// Answer each request body with `handler`, which may return malformed
+    /// JSON, invalid UTF-8 or any error, to exercise the client's failure handling.
+    pub fn raw(handler: impl Fn(&str) -> Result<Vec<u8>> + Send + Sync + 'static) -> Self {
+        Self {
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&self, body: &str) -> Result<Vec<u8>> {
+        (self.handler)(body)
+    }
+
+    fn endpoint(&self) -> &str {
+        "memory"
+    }
+}
+
+impl fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MemoryTransport")
+    }
+}
+
+pub(crate) fn read_error(e: impl fmt::Display) -> ThunderbirdError {
+    ThunderbirdError::Protocol {
+        code: INTERNAL_ERROR,
+        message: format!("Failed to read response body: {}", e),
+    }
+}
+
+pub(crate) fn too_large(limit: u64) -> ThunderbirdError {
+    ThunderbirdError::InvalidResponse(format!(
+        "Response from Thunderbird exceeds {} bytes; raise max_response_mb in config.toml",
+        limit
+    ))
+}
+
+/// Map HTTP error statuses from the extension; 401 means a missing or wrong token.
+pub(crate) fn check_status(status: u16, data: &[u8]) -> Result<()> {
+    if (200..300).contains(&status) {
+        return Ok(());
+    }
+    let data = String::from_utf8_lossy(data);
+    if status == 401 {
+        return Err(ThunderbirdError::Auth(data.trim().to_string()));
+    }
+    Err(ThunderbirdError::Protocol {
+        code: INTERNAL_ERROR,
+        message: format!("HTTP {} from Thunderbird: {}", status, data.trim()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unauthorized_is_auth_error() {
+        let err = check_status(401, b"Invalid API token\n").unwrap_err();
+        assert!(matches!(&err, ThunderbirdError::Auth(m) if m == "Invalid API token"));
+        assert_eq!(err.exit_code(), 9);
+    }
+
+    #[test]
+    fn http_error_is_protocol_error() {
+        let err = check_status(405, b"POST only").unwrap_err();
+        assert_eq!(err.to_string(), "HTTP 405 from Thunderbird: POST only");
+    }
+}
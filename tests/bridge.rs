@@ -0,0 +1,1114 @@
+mod common;
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use thunderbird_api::mcp::resources::ResourceUri;
+use thunderbird_api::testkit::{FakeThunderbird, Fault, INBOX};
+
+/// Feed `lines` to the bridge on stdin and return every JSON line it prints.
+fn bridge(args: &[&str], lines: &[Value]) -> Vec<Value> {
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for line in lines {
+            writeln!(stdin, "{}", line).unwrap();
+        }
+    }
+    let out = child.wait_with_output().unwrap();
+    String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+/// `initialize` and `notifications/initialized`, then `lines`. Returns only
+/// the responses to `lines`, without notifications, sorted by id since calls
+/// finish in any order.
+fn session(args: &[&str], lines: &[Value]) -> Vec<Value> {
+    let mut all = vec![
+        call(0, "initialize", json!({ "protocolVersion": "2025-06-18" })),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    ];
+    all.extend_from_slice(lines);
+    let responses = bridge(args, &all)
+        .into_iter()
+        .filter(|l| l.get("id").is_some());
+    let mut out = by_id(responses.collect());
+    assert_eq!(out.remove(0)["id"], 0);
+    out
+}
+
+fn by_id(mut out: Vec<Value>) -> Vec<Value> {
+    out.sort_by_key(|v| v["id"].as_u64());
+    out
+}
+
+fn call(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+#[test]
+fn session_against_fake_extension() {
+    let fake = FakeThunderbird::start();
+    let out = by_id(bridge(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            call(1, "initialize", json!({ "protocolVersion": "2024-11-05" })),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            call(2, "tools/list", json!({})),
+            call(
+                3,
+                "tools/call",
+                json!({ "name": "searchMessages", "arguments": { "query": "lunch" } }),
+            ),
+        ],
+    ));
+
+    // The notification produces no output.
+    assert_eq!(out.len(), 3);
+    assert_eq!(out[0]["id"], 1);
+    assert_eq!(out[0]["result"]["serverInfo"]["name"], "thunderbird-bridge");
+    assert_eq!(out[0]["result"]["protocolVersion"], "2024-11-05");
+
+    assert_eq!(out[1]["id"], 2);
+    let tools = out[1]["result"]["tools"].as_array().unwrap();
+    assert!(tools.iter().any(|t| t["name"] == "updateMessage"));
+    // 2024-11-05 has no structured output
+    assert!(tools.iter().all(|t| t.get("outputSchema").is_none()));
+
+    assert_eq!(out[2]["id"], 3);
+    let text = out[2]["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("18 Feb 2026 09:05  Carol Singer <carol@example...\n"));
+    assert!(text.contains(&format!("id: lunch-42@example.com  folder: {}", INBOX)));
+    assert!(out[2]["result"].get("structuredContent").is_none());
+    // The full result follows as JSON, for clients without structuredContent
+    let json = out[2]["result"]["content"][1]["text"].as_str().unwrap();
+    let json: Value = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        json["messages"][0]["author"],
+        "Carol Singer <carol@example.com>"
+    );
+}
+
+#[test]
+fn structured_tool_output() {
+    let fake = FakeThunderbird::start();
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            call(1, "tools/list", json!({})),
+            call(
+                2,
+                "tools/call",
+                json!({
+                    "name": "getMessage",
+                    "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX }
+                }),
+            ),
+            call(
+                3,
+                "tools/call",
+                json!({ "name": "listFolders", "arguments": {} }),
+            ),
+        ],
+    );
+
+    let tools = out[0]["result"]["tools"].as_array().unwrap();
+    assert!(tools.iter().all(|t| t["outputSchema"]["type"] == "object"));
+
+    let message = &out[1]["result"];
+    assert_eq!(message["structuredContent"]["subject"], "Quarterly report");
+    assert_eq!(
+        message["structuredContent"]["attachments"][0]["name"],
+        "report.pdf"
+    );
+    let text = message["content"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Subject: Quarterly report [UNREAD]\n"));
+
+    let folders = &out[2]["result"]["structuredContent"]["folders"];
+    assert_eq!(folders.as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn parse_error_keeps_going() {
+    let fake = FakeThunderbird::start();
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--endpoint", &fake.endpoint()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        writeln!(stdin, "{{not json").unwrap();
+        writeln!(stdin, "{}", call(2, "ping", json!({}))).unwrap();
+    }
+    let out = child.wait_with_output().unwrap();
+    let lines: Vec<Value> = String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines[0]["error"]["code"], -32700);
+    assert_eq!(lines[1]["id"], 2);
+    assert_eq!(lines[1]["result"], json!({}));
+}
+
+#[test]
+fn requests_before_initialize_are_rejected() {
+    let fake = FakeThunderbird::start();
+    let out = bridge(
+        &["--endpoint", &fake.endpoint()],
+        &[call(1, "tools/list", json!({}))],
+    );
+    assert_eq!(out[0]["error"]["code"], -32600);
+    assert!(fake.requests().is_empty());
+}
+
+#[test]
+fn extension_errors_are_passed_through() {
+    let fake = FakeThunderbird::start();
+    fake.inject(
+        "getMessage",
+        Fault::RpcError {
+            code: -32000,
+            message: "Error: boom".into(),
+        },
+    );
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[call(
+            7,
+            "tools/call",
+            json!({ "name": "getMessage", "arguments": { "messageId": "x", "folderPath": INBOX } }),
+        )],
+    );
+    assert_eq!(out[0]["id"], 7);
+    assert_eq!(out[0]["error"]["code"], -32000);
+    assert_eq!(out[0]["error"]["message"], "Error: boom");
+}
+
+#[test]
+fn folders_and_messages_as_resources() {
+    let fake = FakeThunderbird::start();
+    let message = ResourceUri::Message {
+        folder_path: INBOX.into(),
+        message_id: "report-q1@example.com".into(),
+    }
+    .to_uri();
+    let missing = ResourceUri::Message {
+        folder_path: INBOX.into(),
+        message_id: "nope@example.com".into(),
+    }
+    .to_uri();
+    let inbox = ResourceUri::Folder(INBOX.into()).to_uri();
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            call(1, "resources/list", json!({})),
+            call(2, "resources/templates/list", json!({})),
+            call(3, "resources/read", json!({ "uri": message })),
+            call(4, "resources/read", json!({ "uri": missing })),
+            call(5, "resources/read", json!({ "uri": inbox })),
+        ],
+    );
+
+    let resources = out[0]["result"]["resources"].as_array().unwrap();
+    assert_eq!(resources[0]["uri"], "thunderbird://account/account1");
+    let listed = resources
+        .iter()
+        .find(|r| r["uri"] == inbox.as_str())
+        .unwrap();
+    assert_eq!(listed["name"], "Inbox");
+
+    assert_eq!(
+        out[1]["result"]["resourceTemplates"][0]["uriTemplate"],
+        "thunderbird://message/{folderPath}/{messageId}"
+    );
+
+    let contents = &out[2]["result"]["contents"][0];
+    assert_eq!(contents["uri"], message.as_str());
+    assert!(contents["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("Subject: Quarterly report [UNREAD]\n"));
+
+    assert_eq!(out[3]["error"]["code"], -32002);
+
+    let folder: Value =
+        serde_json::from_str(out[4]["result"]["contents"][0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(folder["totalMessages"], 4);
+}
+
+#[test]
+fn subscribed_folder_change_is_notified() {
+    let fake = FakeThunderbird::start();
+    let inbox = ResourceUri::Folder(INBOX.into()).to_uri();
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--endpoint", &fake.endpoint(), "--poll-secs", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut exchange = |line: Value| {
+        writeln!(stdin, "{}", line).unwrap();
+        serde_json::from_str::<Value>(&stdout.next().unwrap().unwrap()).unwrap()
+    };
+
+    let init = exchange(call(0, "initialize", json!({})));
+    assert_eq!(
+        init["result"]["capabilities"]["resources"]["subscribe"],
+        true
+    );
+    // Subscribe before changing anything, so the change is after the baseline
+    assert_eq!(
+        exchange(call(1, "resources/subscribe", json!({ "uri": inbox })))["result"],
+        json!({})
+    );
+    let mut lines = vec![exchange(call(
+        2,
+        "tools/call",
+        json!({
+            "name": "updateMessage",
+            "arguments": { "messageId": "meeting-7@example.com", "folderPath": INBOX, "read": true }
+        }),
+    ))];
+    // The next poll, within a second or so
+    lines.push(serde_json::from_str(&stdout.next().unwrap().unwrap()).unwrap());
+    drop(stdin);
+    child.wait().unwrap();
+
+    let updates: Vec<_> = lines
+        .iter()
+        .filter(|l| l["method"] == "notifications/resources/updated")
+        .collect();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0]["params"]["uri"], inbox.as_str());
+}
+
+#[test]
+fn unsubscribe_overtaking_subscribe_sticks() {
+    let fake = FakeThunderbird::start();
+    // Keep the subscribe's first listFolders busy while the unsubscribe is read
+    fake.inject("listFolders", Fault::Delay(Duration::from_millis(300)));
+    let inbox = ResourceUri::Folder(INBOX.into()).to_uri();
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--endpoint", &fake.endpoint(), "--poll-secs", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut read = || serde_json::from_str::<Value>(&stdout.next().unwrap().unwrap()).unwrap();
+
+    writeln!(stdin, "{}", call(0, "initialize", json!({}))).unwrap();
+    read();
+    writeln!(
+        stdin,
+        "{}",
+        call(1, "resources/subscribe", json!({ "uri": inbox }))
+    )
+    .unwrap();
+    writeln!(
+        stdin,
+        "{}",
+        call(2, "resources/unsubscribe", json!({ "uri": inbox }))
+    )
+    .unwrap();
+    let mut ids = vec![read()["id"].clone(), read()["id"].clone()];
+    ids.sort_by_key(|id| id.as_i64());
+    assert_eq!(ids, [json!(1), json!(2)]);
+
+    let update = json!({
+        "name": "updateMessage",
+        "arguments": { "messageId": "meeting-7@example.com", "folderPath": INBOX, "read": true }
+    });
+    writeln!(stdin, "{}", call(3, "tools/call", update)).unwrap();
+    assert_eq!(read()["id"], 3);
+    // Two polls' time: a stale subscription would have been notified by now
+    std::thread::sleep(Duration::from_millis(2500));
+    writeln!(stdin, "{}", call(4, "ping", json!({}))).unwrap();
+    assert_eq!(read()["id"], 4);
+    drop(stdin);
+    child.wait().unwrap();
+}
+
+#[test]
+fn only_tool_calls_are_audited() {
+    let fake = FakeThunderbird::start();
+    let log = common::temp_file("bridge-audit.jsonl", "");
+    let folder = ResourceUri::Folder(INBOX.into()).to_uri();
+    let out = session(
+        &[
+            "--endpoint",
+            &fake.endpoint(),
+            "--audit-log",
+            log.to_str().unwrap(),
+        ],
+        &[
+            call(1, "resources/subscribe", json!({ "uri": folder })),
+            call(
+                2,
+                "completion/complete",
+                json!({
+                    "ref": { "type": "ref/prompt", "name": "draft-reply" },
+                    "argument": { "name": "folderPath", "value": "in" }
+                }),
+            ),
+            call(3, "resources/read", json!({ "uri": folder })),
+            call(
+                4,
+                "tools/call",
+                json!({
+                    "name": "createEvent",
+                    "arguments": {
+                        "title": "Review",
+                        "startDate": "2026-03-02T10:00:00Z",
+                        "description": "Door code 1234"
+                    }
+                }),
+            ),
+        ],
+    );
+    assert_eq!(out.len(), 4);
+
+    let entries: Vec<Value> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 1, "{:?}", entries);
+    assert_eq!(entries[0]["tool"], "createEvent");
+    assert_eq!(entries[0]["caller"], "thunderbird-api");
+    assert_eq!(
+        entries[0]["arguments"]["description"],
+        "[14 chars redacted]"
+    );
+}
+
+#[test]
+fn refused_calls_are_audited() {
+    let fake = FakeThunderbird::start();
+    let log = common::temp_file("refused-audit.jsonl", "");
+    let config = common::temp_file("refused.toml", "confirm_fallback = \"deny\"\n");
+    let out = session(
+        &[
+            "--endpoint",
+            &fake.endpoint(),
+            "--config",
+            config.to_str().unwrap(),
+            "--audit-log",
+            log.to_str().unwrap(),
+            "--deny-tool",
+            "sendMail",
+        ],
+        &[
+            call(
+                1,
+                "tools/call",
+                json!({ "name": "sendMail", "arguments": { "to": "bob@example.com", "body": "Hi" } }),
+            ),
+            call(
+                2,
+                "tools/call",
+                json!({
+                    "name": "updateMessage",
+                    "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX, "trash": true }
+                }),
+            ),
+        ],
+    );
+    assert_eq!(out.len(), 2);
+
+    let entries: Vec<Value> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2, "{:?}", entries);
+    assert_eq!(entries[0]["tool"], "sendMail");
+    assert_eq!(entries[0]["status"], "refused");
+    assert_eq!(entries[0]["arguments"]["body"], "[2 chars redacted]");
+    assert_eq!(
+        entries[0]["error"],
+        "Tool sendMail is not allowed by the bridge's full tool profile"
+    );
+    assert_eq!(entries[1]["tool"], "updateMessage");
+    assert_eq!(entries[1]["status"], "refused");
+    assert_eq!(
+        entries[1]["error"],
+        "updateMessage needs the user's confirmation, which this MCP client cannot ask for"
+    );
+}
+
+#[test]
+fn trash_is_confirmed_with_the_user() {
+    use thunderbird_api::testkit::TRASH;
+
+    let fake = FakeThunderbird::start();
+    let log = common::temp_file("confirm-audit.jsonl", "");
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--endpoint", &fake.endpoint()])
+        .args(["--audit-log", log.to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut exchange = |line: Value| {
+        writeln!(stdin, "{}", line).unwrap();
+        serde_json::from_str::<Value>(&stdout.next().unwrap().unwrap()).unwrap()
+    };
+    let trash = |id| {
+        call(
+            id,
+            "tools/call",
+            json!({
+                "name": "updateMessage",
+                "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX, "trash": true }
+            }),
+        )
+    };
+    let answer = |request: &Value, result: Value| json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+
+    let capabilities = json!({ "capabilities": { "elicitation": {} } });
+    exchange(call(0, "initialize", capabilities));
+    let asked = exchange(trash(1));
+    assert_eq!(asked["method"], "elicitation/create");
+    assert_eq!(
+        asked["params"]["message"],
+        "Move this message to Trash?\n\nFrom: Bob Builder <bob@example.com>\nSubject: Quarterly report"
+    );
+    let declined = exchange(answer(&asked, json!({ "action": "decline" })));
+    assert_eq!(declined["id"], 1);
+    assert_eq!(declined["result"]["isError"], true);
+    assert!(fake
+        .mailbox()
+        .find("report-q1@example.com", INBOX)
+        .is_some());
+
+    let asked = exchange(trash(2));
+    let accepted = exchange(answer(
+        &asked,
+        json!({ "action": "accept", "content": { "confirm": true } }),
+    ));
+    assert_eq!(accepted["id"], 2);
+    assert!(accepted["result"].get("isError").is_none());
+    drop(stdin);
+    child.wait().unwrap();
+    assert!(fake
+        .mailbox()
+        .find("report-q1@example.com", TRASH)
+        .is_some());
+    let statuses: Vec<_> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str::<Value>(l).unwrap()["status"].clone())
+        .collect();
+    assert_eq!(statuses, ["declined", "ok"]);
+
+    // Without elicitation the configured fallback applies
+    let config = common::temp_file("confirm.toml", "confirm_fallback = \"deny\"\n");
+    let out = session(
+        &[
+            "--endpoint",
+            &fake.endpoint(),
+            "--config",
+            config.to_str().unwrap(),
+        ],
+        &[call(
+            1,
+            "tools/call",
+            json!({
+                "name": "updateMessage",
+                "arguments": { "messageId": "lunch-42@example.com", "folderPath": INBOX, "moveTo": TRASH }
+            }),
+        )],
+    );
+    assert_eq!(out[0]["result"]["isError"], true);
+    assert_eq!(
+        out[0]["result"]["content"][0]["text"],
+        "updateMessage needs the user's confirmation, which this MCP client cannot ask for"
+    );
+}
+
+#[test]
+fn pending_confirmation_holds_no_worker() {
+    let fake = FakeThunderbird::start();
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--endpoint", &fake.endpoint(), "--workers", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut exchange = |line: Value| {
+        writeln!(stdin, "{}", line).unwrap();
+        serde_json::from_str::<Value>(&stdout.next().unwrap().unwrap()).unwrap()
+    };
+
+    let capabilities = json!({ "capabilities": { "elicitation": {} } });
+    exchange(call(0, "initialize", capabilities));
+    let asked = exchange(call(
+        1,
+        "tools/call",
+        json!({
+            "name": "updateMessage",
+            "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX, "trash": true }
+        }),
+    ));
+    assert_eq!(asked["method"], "elicitation/create");
+    // The only worker is free while the user decides
+    let folders = exchange(call(
+        2,
+        "tools/call",
+        json!({ "name": "listFolders", "arguments": {} }),
+    ));
+    assert_eq!(folders["id"], 2);
+
+    // Cancelling the call withdraws the question and drops the call
+    let withdrawn = exchange(json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/cancelled",
+        "params": { "requestId": 1 }
+    }));
+    assert_eq!(withdrawn["method"], "notifications/cancelled");
+    assert_eq!(withdrawn["params"]["requestId"], asked["id"]);
+    let started = Instant::now();
+    drop(stdin);
+    child.wait().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(stdout.next().is_none());
+    assert!(fake
+        .mailbox()
+        .find("report-q1@example.com", INBOX)
+        .is_some());
+}
+
+#[test]
+fn slow_calls_do_not_block_others() {
+    let fake = FakeThunderbird::start();
+    fake.inject("getMessage", Fault::Delay(Duration::from_millis(1500)));
+    let get = json!({
+        "name": "getMessage",
+        "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX }
+    });
+    let out = bridge(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            call(0, "initialize", json!({})),
+            call(1, "tools/call", get.clone()),
+            call(2, "ping", json!({})),
+            call(
+                3,
+                "tools/call",
+                json!({ "name": "listFolders", "arguments": {} }),
+            ),
+            call(4, "tools/call", get),
+            json!({ "jsonrpc": "2.0", "method": "notifications/cancelled", "params": { "requestId": 4 } }),
+        ],
+    );
+
+    let ids: Vec<_> = out.iter().map(|l| l["id"].as_u64().unwrap()).collect();
+    // Cancelled calls get no response
+    assert_eq!(ids.len(), 4, "{:?}", ids);
+    assert_eq!(ids[0], 0);
+    assert_eq!(ids.last(), Some(&1));
+    assert!(ids.contains(&2) && ids.contains(&3));
+}
+
+#[test]
+fn requests_without_a_method_are_invalid() {
+    let out = session(
+        &[],
+        &[
+            json!({ "jsonrpc": "2.0", "id": 1 }),
+            json!({ "jsonrpc": "2.0", "id": 2, "result": {} }),
+        ],
+    );
+    assert_eq!(out.len(), 2, "{:?}", out);
+    for response in &out {
+        assert_eq!(response["error"]["code"], -32600);
+        assert_eq!(response["error"]["message"], "Invalid Request");
+    }
+}
+
+#[test]
+fn pending_request_ids_are_not_reused() {
+    let fake = FakeThunderbird::start();
+    fake.inject("getMessage", Fault::Delay(Duration::from_millis(500)));
+    let get = json!({
+        "name": "getMessage",
+        "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX }
+    });
+    let out = bridge(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            call(0, "initialize", json!({})),
+            call(1, "tools/call", get.clone()),
+            call(1, "tools/call", get),
+        ],
+    );
+
+    assert_eq!(out.len(), 3, "{:?}", out);
+    assert_eq!(out[1]["id"], 1);
+    assert_eq!(out[1]["error"]["code"], -32600);
+    assert!(out[2]["result"]["content"].is_array());
+}
+
+#[test]
+fn tool_profile_hides_and_refuses_tools() {
+    let fake = FakeThunderbird::start();
+    let config = common::temp_file(
+        "profile.toml",
+        "tool_profile = \"readonly\"\ndeny_tools = [\"getMessage\"]\n",
+    );
+    let config = config.to_str().unwrap();
+    let out = session(
+        &[
+            "--endpoint",
+            &fake.endpoint(),
+            "--config",
+            config,
+            "--allow-tool",
+            "createEvent",
+        ],
+        &[
+            call(1, "tools/list", json!({})),
+            call(
+                2,
+                "tools/call",
+                json!({
+                    "name": "updateMessage",
+                    "arguments": { "messageId": "report-q1@example.com", "folderPath": INBOX, "trash": true }
+                }),
+            ),
+            call(
+                3,
+                "tools/call",
+                json!({ "name": "listFolders", "arguments": {} }),
+            ),
+        ],
+    );
+
+    let mut names: Vec<_> = out[0]["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "createEvent",
+            "listAccounts",
+            "listCalendars",
+            "listFolders",
+            "searchContacts",
+            "searchMessages"
+        ]
+    );
+    assert_eq!(out[1]["error"]["code"], -32602);
+    assert_eq!(
+        out[1]["error"]["message"],
+        "Tool updateMessage is not allowed by the bridge's readonly tool profile"
+    );
+    assert!(out[2]["result"]["content"].is_array());
+}
+
+#[test]
+fn denied_tools_cannot_be_read_as_resources() {
+    let fake = FakeThunderbird::start();
+    let message = ResourceUri::Message {
+        folder_path: INBOX.into(),
+        message_id: "report-q1@example.com".into(),
+    }
+    .to_uri();
+    let out = session(
+        &[
+            "--endpoint",
+            &fake.endpoint(),
+            "--deny-tool",
+            "getMessage",
+            "--deny-tool",
+            "listFolders",
+        ],
+        &[
+            call(1, "resources/read", json!({ "uri": message })),
+            call(
+                2,
+                "completion/complete",
+                json!({
+                    "ref": { "type": "ref/resource", "uri": "thunderbird://message/{folderPath}/{messageId}" },
+                    "argument": { "name": "folderPath", "value": "in" }
+                }),
+            ),
+            call(3, "resources/list", json!({})),
+        ],
+    );
+
+    assert_eq!(out[0]["error"]["code"], -32602);
+    assert_eq!(
+        out[0]["error"]["message"],
+        format!(
+            "Cannot read {}: Tool getMessage is not allowed by the bridge's full tool profile",
+            message
+        )
+    );
+    assert_eq!(out[1]["result"]["completion"]["values"], json!([]));
+    let uris: Vec<_> = out[2]["result"]["resources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["uri"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        uris,
+        [
+            "thunderbird://account/account1",
+            "thunderbird://account/account2"
+        ]
+    );
+}
+
+#[test]
+fn tools_are_annotated() {
+    let fake = FakeThunderbird::start();
+    let config = common::temp_file(
+        "annotations.toml",
+        "[tool_annotations.sendMail]\nopen_world_hint = false\n",
+    );
+    let args = [
+        "--endpoint",
+        &fake.endpoint(),
+        "--config",
+        config.to_str().unwrap(),
+    ];
+    let out = session(&args, &[call(1, "tools/list", json!({}))]);
+    let tools = out[0]["result"]["tools"].as_array().unwrap();
+    let annotations =
+        |name: &str| tools.iter().find(|t| t["name"] == name).unwrap()["annotations"].clone();
+    assert_eq!(annotations("searchMessages")["readOnlyHint"], true);
+    assert_eq!(annotations("updateMessage")["destructiveHint"], true);
+    assert_eq!(annotations("sendMail")["openWorldHint"], false);
+
+    // Annotations came with 2025-03-26
+    let old = bridge(
+        &args,
+        &[
+            call(0, "initialize", json!({ "protocolVersion": "2024-11-05" })),
+            call(1, "tools/list", json!({})),
+        ],
+    );
+    let tools = old[1]["result"]["tools"].as_array().unwrap();
+    assert!(tools.iter().all(|t| t.get("annotations").is_none()));
+}
+
+#[test]
+fn tool_failures_are_error_results() {
+    let fake = FakeThunderbird::start();
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[call(
+            1,
+            "tools/call",
+            json!({
+                "name": "updateMessage",
+                "arguments": { "messageId": "nope@example.com", "folderPath": INBOX, "read": true }
+            }),
+        )],
+    );
+    assert!(out[0].get("error").is_none());
+    assert_eq!(out[0]["result"]["isError"], true);
+    assert_eq!(
+        out[0]["result"]["content"][0]["text"],
+        "updateMessage failed: Message not found: nope@example.com"
+    );
+}
+
+#[test]
+fn not_running_is_reported_per_request() {
+    let port = common::closed_port().to_string();
+    let out = session(
+        &["--port", &port, "--retry-secs", "0"],
+        &[call(1, "tools/list", json!({}))],
+    );
+    assert_eq!(out[0]["id"], 1);
+    let message = out[0]["error"]["message"].as_str().unwrap();
+    assert!(message.contains("Is Thunderbird running"), "{}", message);
+}
+
+#[test]
+fn lossy_results_are_marked() {
+    let fake = FakeThunderbird::start();
+    fake.inject("getMessage", Fault::InvalidUtf8);
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[call(
+            1,
+            "tools/call",
+            json!({
+                "name": "getMessage",
+                "arguments": { "messageId": "lunch-42@example.com", "folderPath": INBOX }
+            }),
+        )],
+    );
+    assert_eq!(out[0]["result"]["_meta"]["thunderbird-api/lossyUtf8"], true);
+    let text = out[0]["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains('\u{FFFD}'));
+}
+
+#[test]
+fn builtin_and_user_prompts() {
+    let config =
+        std::env::temp_dir().join(format!("thunderbird-api-prompts-{}", std::process::id()));
+    let dir = config.join("thunderbird-api/prompts");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("flagged.toml"),
+        r#"
+description = "Flagged mail from one sender"
+template = "Use searchMessages for {{sender}} and list the flagged ones."
+
+[[arguments]]
+name = "sender"
+required = true
+"#,
+    )
+    .unwrap();
+
+    let fake = FakeThunderbird::start();
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--endpoint", &fake.endpoint()])
+        .env("XDG_CONFIG_HOME", &config)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for line in [
+            call(0, "initialize", json!({})),
+            call(1, "prompts/list", json!({})),
+            call(
+                2,
+                "prompts/get",
+                json!({ "name": "flagged", "arguments": { "sender": "carol" } }),
+            ),
+            call(3, "prompts/get", json!({ "name": "summarize-thread" })),
+        ] {
+            writeln!(stdin, "{}", line).unwrap();
+        }
+    }
+    let out = child.wait_with_output().unwrap();
+    std::fs::remove_dir_all(&config).ok();
+    let lines: Vec<Value> = String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+
+    let prompts = lines[1]["result"]["prompts"].as_array().unwrap();
+    let names: Vec<_> = prompts
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"draft-reply"));
+    assert!(names.contains(&"flagged"));
+
+    assert_eq!(
+        lines[2]["result"]["messages"][0]["content"]["text"],
+        "Use searchMessages for carol and list the flagged ones."
+    );
+    assert_eq!(lines[3]["error"]["code"], -32602);
+}
+
+#[test]
+fn folder_paths_are_completed() {
+    use thunderbird_api::testkit::ARCHIVE;
+
+    let fake = FakeThunderbird::start();
+    let complete = |id, reference: Value, value: &str| {
+        call(
+            id,
+            "completion/complete",
+            json!({ "ref": reference, "argument": { "name": "folderPath", "value": value } }),
+        )
+    };
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            complete(
+                1,
+                json!({ "type": "ref/prompt", "name": "draft-reply" }),
+                "arch",
+            ),
+            complete(
+                2,
+                json!({ "type": "ref/resource", "uri": "thunderbird://message/{folderPath}/{messageId}" }),
+                "",
+            ),
+            complete(3, json!({ "type": "ref/prompt", "name": "nope" }), ""),
+        ],
+    );
+
+    assert_eq!(out[0]["result"]["completion"]["values"], json!([ARCHIVE]));
+    let all = out[1]["result"]["completion"]["values"].as_array().unwrap();
+    assert!(!all.contains(&json!(INBOX)));
+    assert_eq!(out[1]["result"]["completion"]["hasMore"], false);
+    assert_eq!(out[2]["error"]["code"], -32602);
+
+    // A completed template value can be put into the URI as it is
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[complete(
+            1,
+            json!({ "type": "ref/resource", "uri": "thunderbird://message/{folderPath}/{messageId}" }),
+            "inbox",
+        )],
+    );
+    let folder = out[0]["result"]["completion"]["values"][0]
+        .as_str()
+        .unwrap();
+    let uri = format!("thunderbird://message/{}/report-q1%40example.com", folder);
+    let out = session(
+        &["--endpoint", &fake.endpoint()],
+        &[call(1, "resources/read", json!({ "uri": uri }))],
+    );
+    assert_eq!(out[0]["result"]["contents"][0]["uri"], uri);
+}
+
+#[test]
+fn long_calls_report_progress() {
+    let fake = FakeThunderbird::start();
+    fake.inject(
+        "searchMessages",
+        Fault::Progress {
+            steps: 5,
+            step: Duration::from_millis(500),
+        },
+    );
+    fake.inject("getMessage", Fault::Delay(Duration::from_millis(2200)));
+    let out = bridge(
+        &["--endpoint", &fake.endpoint()],
+        &[
+            call(0, "initialize", json!({})),
+            call(
+                1,
+                "tools/call",
+                json!({
+                    "name": "searchMessages",
+                    "arguments": { "query": "lunch" },
+                    "_meta": { "progressToken": "search" }
+                }),
+            ),
+            call(
+                2,
+                "tools/call",
+                json!({
+                    "name": "getMessage",
+                    "arguments": { "messageId": "lunch-42@example.com", "folderPath": INBOX },
+                    "_meta": { "progressToken": 7 }
+                }),
+            ),
+        ],
+    );
+
+    for (id, token) in [(1, json!("search")), (2, json!(7))] {
+        let answered = out.iter().position(|l| l["id"] == id).unwrap();
+        let notes: Vec<_> = out
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| {
+                l["method"] == "notifications/progress" && l["params"]["progressToken"] == token
+            })
+            .collect();
+        assert!(notes.len() >= 2, "{:?}", out);
+        assert!(notes.iter().all(|(i, _)| *i < answered));
+        let progress: Vec<_> = notes
+            .iter()
+            .map(|(_, l)| l["params"]["progress"].as_u64().unwrap())
+            .collect();
+        assert!(progress.windows(2).all(|w| w[0] < w[1]), "{:?}", progress);
+    }
+
+    // Relayed from the extension for the search, heartbeats for the rest
+    let messages: Vec<_> = out
+        .iter()
+        .filter(|l| l["method"] == "notifications/progress")
+        .map(|l| l["params"]["message"].as_str().unwrap())
+        .collect();
+    assert!(messages.iter().any(|m| m.starts_with("Searched ")));
+    assert!(messages.contains(&"Waiting for Thunderbird"));
+}
+
+#[test]
+fn connection_problems_are_logged() {
+    let port = common::closed_port().to_string();
+    let mut child = common::command(env!("CARGO_BIN_EXE_thunderbird-api"))
+        .args(["--port", &port, "--retry-secs", "1"])
+        .env("THUNDERBIRD_API_LOG", "error")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for line in [
+            call(0, "initialize", json!({})),
+            call(1, "logging/setLevel", json!({ "level": "info" })),
+            call(
+                2,
+                "tools/call",
+                json!({ "name": "listFolders", "arguments": {} }),
+            ),
+            call(3, "logging/setLevel", json!({ "level": "chatty" })),
+        ] {
+            writeln!(stdin, "{}", line).unwrap();
+        }
+    }
+    let out = child.wait_with_output().unwrap();
+    let lines: Vec<Value> = String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+
+    assert!(lines[0]["result"]["capabilities"]["logging"].is_object());
+    let levels: Vec<_> = lines
+        .iter()
+        .filter(|l| l["method"] == "notifications/message")
+        .map(|l| l["params"]["level"].as_str().unwrap())
+        .collect();
+    assert!(levels.contains(&"info"), "{:?}", lines);
+    assert_eq!(levels.last(), Some(&"error"));
+    let invalid = lines.iter().find(|l| l["id"] == 3).unwrap();
+    assert_eq!(invalid["error"]["code"], -32602);
+
+    // stderr keeps the level from the environment
+    let stderr: Vec<Value> = String::from_utf8(out.stderr)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(stderr.len(), 1);
+    assert_eq!(stderr[0]["level"], "error");
+    assert_eq!(stderr[0]["data"]["method"], "listFolders");
+}
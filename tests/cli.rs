@@ -0,0 +1,321 @@
+mod common;
+
+use std::process::Output;
+use std::time::Duration;
+
+use thunderbird_api::testkit::{FakeThunderbird, Fault, Mailbox, INBOX, TRASH};
+
+fn cli(fake: &FakeThunderbird, args: &[&str]) -> Output {
+    common::command(env!("CARGO_BIN_EXE_thunderbird-cli"))
+        .arg("--endpoint")
+        .arg(fake.endpoint())
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn search_prints_matches() {
+    let fake = FakeThunderbird::start();
+    let out = cli(&fake, &["search", "quarterly", "--sort", "asc"]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let text = stdout(&out);
+    assert!(text.contains("20 Nov 2025 10:00  Bob Builder <bob@example.com>"));
+    assert!(text.contains("Quarterly report [UNREAD]"));
+    assert!(text.ends_with("2 message(s)\n"));
+    assert!(text.find("Q4").unwrap() < text.find("Quarterly report [UNREAD]").unwrap());
+}
+
+#[test]
+fn get_prints_message() {
+    let fake = FakeThunderbird::start();
+    let out = cli(&fake, &["get", "report-q1@example.com", INBOX]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    let text = stdout(&out);
+    assert!(text.contains("Subject: Quarterly report [UNREAD]"));
+    assert!(text.contains("CC:      carol@example.com"));
+    assert!(text.contains("report.pdf (47.1KB)"));
+}
+
+#[test]
+fn missing_message_exits_not_found() {
+    let fake = FakeThunderbird::start();
+    let out = cli(&fake, &["get", "nope@example.com", INBOX]);
+    assert_eq!(out.status.code(), Some(7));
+    assert_eq!(stderr(&out), "Error: Message not found: nope@example.com\n");
+}
+
+#[test]
+fn trash_moves_message() {
+    let fake = FakeThunderbird::start();
+    let out = cli(
+        &fake,
+        &[
+            "update",
+            "lunch-42@example.com",
+            INBOX,
+            "--unread",
+            "--trash",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(stdout(&out), "Done: marked unread, trashed\n");
+    let moved = fake.mailbox();
+    let moved = moved.find("lunch-42@example.com", TRASH).unwrap();
+    assert!(!moved.message.read);
+}
+
+#[test]
+fn send_opens_compose_window() {
+    let fake = FakeThunderbird::start();
+    let out = cli(
+        &fake,
+        &[
+            "send",
+            "--to",
+            "bob@example.com",
+            "--subject",
+            "Hi",
+            "--body",
+            "Hello",
+        ],
+    );
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(stdout(&out), "Compose window opened\n");
+    let composed = fake.mailbox().composed;
+    assert_eq!(composed[0].0, "sendMail");
+    assert_eq!(composed[0].1["subject"], "Hi");
+}
+
+#[test]
+fn attachments_are_checked() {
+    let docs = std::env::temp_dir().join(format!("thunderbird-api-{}-docs", std::process::id()));
+    std::fs::create_dir_all(&docs).unwrap();
+    std::fs::write(docs.join("report.pdf"), "%PDF").unwrap();
+    std::fs::write(docs.join(".env"), "SECRET=1").unwrap();
+    let outside = common::temp_file("outside.pdf", "%PDF");
+    let config = common::temp_file(
+        "attachments.toml",
+        &format!("attachment_dirs = [{:?}]\n", docs),
+    );
+    let fake = FakeThunderbird::start();
+    let send = |file: &std::path::Path| {
+        let config = config.to_str().unwrap();
+        let file = file.to_str().unwrap();
+        cli(
+            &fake,
+            &[
+                "--config",
+                config,
+                "send",
+                "--to",
+                "bob@example.com",
+                "--attach",
+                file,
+            ],
+        )
+    };
+
+    let out = send(&docs.join("report.pdf"));
+    assert!(out.status.success(), "{}", stderr(&out));
+    let out = send(&outside);
+    assert_eq!(out.status.code(), Some(6));
+    assert!(stderr(&out).contains("is outside the allowed directories"));
+    let out = send(&docs.join(".env"));
+    assert_eq!(out.status.code(), Some(6));
+    assert!(stderr(&out).contains("matches the denied pattern .*"));
+
+    let composed = fake.mailbox().composed;
+    assert_eq!(composed.len(), 1);
+    let attached = std::fs::canonicalize(docs.join("report.pdf")).unwrap();
+    assert_eq!(composed[0].1["attachments"][0], attached.to_str().unwrap());
+}
+
+#[test]
+fn stale_port_file_is_named() {
+    let run = std::env::temp_dir().join(format!("thunderbird-api-{}-run", std::process::id()));
+    std::fs::create_dir_all(run.join("thunderbird-api")).unwrap();
+    let port_file = run.join("thunderbird-api").join("port");
+    std::fs::write(&port_file, format!("{}\n", common::closed_port())).unwrap();
+    let out = common::command(env!("CARGO_BIN_EXE_thunderbird-cli"))
+        .env("XDG_RUNTIME_DIR", &run)
+        .arg("accounts")
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    let message = format!("the port was read from {}", port_file.display());
+    assert!(stderr(&out).contains(&message), "{}", stderr(&out));
+}
+
+#[test]
+fn usage_errors_keep_clap_exit_code() {
+    let fake = FakeThunderbird::start();
+    let out = cli(&fake, &["search", "--no-such-flag"]);
+    assert_eq!(out.status.code(), Some(2));
+    let help = stdout(&cli(&fake, &["--help"]));
+    assert!(help.contains("  2  invalid command-line usage\n"));
+    assert!(help.contains("  10 invalid tool arguments"));
+}
+
+#[test]
+fn not_running_exit_code() {
+    let out = common::command(env!("CARGO_BIN_EXE_thunderbird-cli"))
+        .args(["--port", &common::closed_port().to_string(), "accounts"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    assert!(stderr(&out).contains("Is Thunderbird running"));
+}
+
+#[test]
+fn slow_extension_times_out() {
+    let fake = FakeThunderbird::start();
+    fake.inject("listFolders", Fault::Delay(Duration::from_secs(3)));
+    let config = common::temp_file("timeout.toml", "timeout_secs = 1\n");
+    let out = cli(&fake, &["--config", config.to_str().unwrap(), "folders"]);
+    assert_eq!(out.status.code(), Some(4), "{}", stderr(&out));
+}
+
+#[test]
+fn control_chars_are_sanitized() {
+    let fake = FakeThunderbird::start();
+    fake.inject("getMessage", Fault::ControlChars);
+    let out = cli(&fake, &["get", "lunch-42@example.com", INBOX]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("Subject: Lunch on Friday?"));
+}
+
+#[test]
+fn invalid_utf8_is_replaced() {
+    let fake = FakeThunderbird::start();
+    fake.inject("getMessage", Fault::InvalidUtf8);
+    let out = cli(&fake, &["get", "lunch-42@example.com", INBOX]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("Subject: Lunch on Friday?"));
+    assert!(stdout(&out).contains('\u{FFFD}'));
+}
+
+fn huge_mailbox() -> Mailbox {
+    let mut mailbox = Mailbox::fixture();
+    // Over ureq's default 10 MB body limit.
+    mailbox.messages[0].message.body = "<p>quarterly numbers</p>\n".repeat(500_000);
+    mailbox.messages[0].message.body_is_html = true;
+    mailbox
+}
+
+#[test]
+fn huge_message_is_read() {
+    let fake = FakeThunderbird::with_mailbox(huge_mailbox());
+    let out = cli(&fake, &["get", "report-q1@example.com", INBOX]);
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).len() > 10_000_000);
+}
+
+#[test]
+fn response_size_limit_is_configurable() {
+    let fake = FakeThunderbird::with_mailbox(huge_mailbox());
+    let config = common::temp_file("limit.toml", "max_response_mb = 1\n");
+    let out = cli(
+        &fake,
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "get",
+            "report-q1@example.com",
+            INBOX,
+        ],
+    );
+    assert_eq!(out.status.code(), Some(5));
+    assert!(stderr(&out).contains("max_response_mb"), "{}", stderr(&out));
+}
+
+#[test]
+fn wrong_token_exits_auth() {
+    let fake = FakeThunderbird::start();
+    fake.require_token("secret");
+    let out = cli(&fake, &["accounts"]);
+    assert_eq!(out.status.code(), Some(9));
+    assert!(stderr(&out).contains("Missing API token"));
+
+    let out = common::command(env!("CARGO_BIN_EXE_thunderbird-cli"))
+        .env("THUNDERBIRD_API_TOKEN", "secret")
+        .args(["--endpoint", &fake.endpoint(), "accounts"])
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert!(stdout(&out).contains("Alice Example <alice@example.com>"));
+}
+
+#[test]
+fn replay_without_thunderbird() {
+    let cassette = common::temp_file("replay.jsonl", "");
+    let cassette = cassette.to_str().unwrap();
+    let recorded = {
+        let fake = FakeThunderbird::start();
+        let out = cli(&fake, &["--record", cassette, "folders"]);
+        assert!(out.status.success(), "{}", stderr(&out));
+        stdout(&out)
+    };
+
+    let out = common::command(env!("CARGO_BIN_EXE_thunderbird-cli"))
+        .args(["--port", &common::closed_port().to_string()])
+        .args(["--replay", cassette, "folders"])
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", stderr(&out));
+    assert_eq!(stdout(&out), recorded);
+    assert!(
+        recorded.contains("Inbox  [4 msgs (2 unread)]"),
+        "{}",
+        recorded
+    );
+}
+
+#[test]
+fn tool_calls_are_audited() {
+    let log = common::temp_file("audit.jsonl", "");
+    let log = log.to_str().unwrap();
+    let fake = FakeThunderbird::start();
+    let audited = |args: &[&str]| {
+        let out = cli(&fake, &[&["--audit-log", log], args].concat());
+        assert!(out.status.success(), "{}", stderr(&out));
+        out
+    };
+    audited(&["update", "lunch-42@example.com", INBOX, "--trash"]);
+    audited(&["send", "--to", "bob@example.com", "--body", "Secret plans"]);
+    let failed = cli(
+        &fake,
+        &["--audit-log", log, "get", "nope@example.com", INBOX],
+    );
+    assert_eq!(failed.status.code(), Some(7));
+
+    let out = audited(&["audit", "--tool", "updateMessage"]);
+    let text = stdout(&out);
+    assert!(
+        text.contains("thunderbird-cli  updateMessage  ok"),
+        "{}",
+        text
+    );
+    assert!(text.contains("  done: trashed\n"), "{}", text);
+    assert!(text.ends_with("1 call(s)\n"));
+
+    let out = audited(&["audit", "--json"]);
+    let entries: Vec<serde_json::Value> = stdout(&out)
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[1]["arguments"]["body"], "[12 chars redacted]");
+    assert_eq!(entries[2]["status"], "error");
+
+    let out = audited(&["audit", "--status", "error", "--last", "1"]);
+    assert!(stdout(&out).contains("error: Message not found: nope@example.com"));
+}
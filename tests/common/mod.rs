@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Run a binary isolated from the user's config, token and port files.
+pub fn command(bin: &str) -> Command {
+    let home = std::env::temp_dir().join(format!("thunderbird-api-test-{}", std::process::id()));
+    let mut cmd = Command::new(bin);
+    cmd.env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_RUNTIME_DIR", home.join("run"))
+        .env_remove("THUNDERBIRD_API_ENDPOINT")
+        .env_remove("THUNDERBIRD_API_PORT")
+        .env_remove("THUNDERBIRD_API_CONFIG")
+        .env_remove("THUNDERBIRD_API_TOKEN")
+        .env_remove("THUNDERBIRD_API_LOG");
+    cmd
+}
+
+/// A port nothing is listening on.
+pub fn closed_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Write `contents` to a file unique to this test process.
+#[allow(dead_code)]
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("thunderbird-api-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}